@@ -1,6 +1,9 @@
-use clap::{Args, Parser, Subcommand};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::profiles::ActiveProfile;
+use crate::profiles::{ActiveProfile, SourceLayer};
 
 #[derive(Parser)]
 #[command(
@@ -9,18 +12,72 @@ use crate::profiles::ActiveProfile;
     about = "A Rust-based command-line tool for dotfiles management with profiles."
 )]
 pub(crate) struct Cli {
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "Use DIR instead of ~/.mntn for backups, registries, profiles and settings (also read from MNTN_DIR)"
+    )]
+    pub mntn_dir: Option<PathBuf>,
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+impl Commands {
+    /// Requirements clap cannot check while parsing, because `config.toml`
+    /// may turn on the flag another one depends on. Run after the settings
+    /// are applied.
+    pub(crate) fn check_requirements(&self) -> Result<(), clap::Error> {
+        let missing = match self {
+            Commands::Backup(args) if args.keep_snapshots.is_some() && !args.snapshot => {
+                Some(("--keep-snapshots", "--snapshot", "snapshot", "[backup]"))
+            }
+            Commands::Sync(args) if args.strategy.is_some() && !args.pull => {
+                Some(("--strategy", "--pull", "pull", "[sync]"))
+            }
+            _ => None,
+        };
+        match missing {
+            Some((flag, needs, key, table)) => Err(Cli::command().error(
+                ErrorKind::MissingRequiredArgument,
+                format!(
+                    "{} requires {} (or {} = true under {} in config.toml)",
+                    flag, needs, key, table
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub(crate) enum Commands {
-    #[command(about = "Backup system configurations and user data to a safe location")]
+    #[command(
+        about = "Backup system configurations and user data to a safe location",
+        after_help = "A backup copy edited directly in ~/.mntn is never overwritten silently: backup asks what to do when run from a terminal, and keeps the edit otherwise."
+    )]
     Backup(BackupArgs),
 
     #[command(about = "Restore system state from a previously created backup")]
     Restore(RestoreArgs),
 
+    #[command(about = "Show differences between backed-up configs and the live files")]
+    Diff(DiffArgs),
+
+    #[command(
+        about = "Open the backup copy of a config entry in $EDITOR",
+        after_help = "The editor is $VISUAL, then $EDITOR, then vi."
+    )]
+    Edit(EditArgs),
+
+    #[command(about = "Show the resolved profile, where it came from, and the paths mntn uses")]
+    Info(InfoArgs),
+
+    #[command(
+        about = "Summarize the profile, registries, last backup, git state, and doctor results"
+    )]
+    Status(StatusArgs),
+
     #[command(about = "Switch to a different profile")]
     Use(UseArgs),
 
@@ -30,7 +87,10 @@ pub(crate) enum Commands {
     #[command(about = "Run git commands in the mntn repository")]
     Git(GitArgs),
 
-    #[command(about = "Stage, commit, and push to the mntn repository")]
+    #[command(
+        about = "Stage, commit, and push to the mntn repository",
+        after_help = "Exit status: 0 on success, 2 if --pull stopped on merge conflicts, 3 if the push was rejected because the remote has new commits, 4 on authentication or network errors, 1 for anything else."
+    )]
     Sync(SyncArgs),
 
     #[command(
@@ -60,14 +120,161 @@ pub(crate) struct BackupArgs {
     pub profile: Option<String>,
     #[arg(
         long,
+        overrides_with = "no_skip_encrypted",
         help = "Skip encrypted configs backup (will not prompt for password)"
     )]
     pub skip_encrypted: bool,
+    #[arg(
+        long,
+        overrides_with = "skip_encrypted",
+        help = "Back up encrypted configs even if config.toml sets skip_encrypted"
+    )]
+    pub no_skip_encrypted: bool,
     #[arg(
         long,
         help = "Always prompt for the encryption password instead of using the one stored in the system keychain"
     )]
     pub ask_password: bool,
+    #[arg(
+        long,
+        overrides_with = "no_snapshot",
+        help = "After backing up, copy the backup tree into ~/.mntn/snapshots/<timestamp>/"
+    )]
+    pub snapshot: bool,
+    #[arg(
+        long,
+        overrides_with = "snapshot",
+        help = "Do not take a snapshot even if config.toml sets snapshot"
+    )]
+    pub no_snapshot: bool,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of snapshots to keep when using --snapshot (older ones are pruned) [default: 5]"
+    )]
+    pub keep_snapshots: Option<u32>,
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of package managers to query at once [default: the CPU count, at most 4]"
+    )]
+    pub jobs: Option<u32>,
+    #[arg(
+        long,
+        overrides_with = "no_ignore_missing_commands",
+        help = "Quietly leave out package managers whose command is not installed instead of warning about each"
+    )]
+    pub ignore_missing_commands: bool,
+    #[arg(
+        long,
+        overrides_with = "ignore_missing_commands",
+        help = "Warn about each missing package manager even if config.toml sets ignore_missing_commands"
+    )]
+    pub no_ignore_missing_commands: bool,
+    #[arg(
+        long,
+        help = "Skip writing manifest.json (file sizes, mtimes, and SHA-256 hashes) into the backup layer"
+    )]
+    pub no_manifest: bool,
+    #[arg(
+        long,
+        help = "Also back up registry entries that are disabled (for this run only)"
+    )]
+    pub include_disabled: bool,
+    #[arg(
+        long = "exclude-entry",
+        value_name = "ID",
+        help = "Leave the registry entry with this id out of the backup (repeatable)"
+    )]
+    pub exclude_entries: Vec<String>,
+    #[arg(
+        long,
+        help = "Choose which config entries to back up from a list (labelled with the layer each backup resolves from)"
+    )]
+    pub interactive: bool,
+    #[arg(
+        long,
+        conflicts_with = "no_manifest",
+        help = "Report profile-layer files that are identical to their common-layer copies"
+    )]
+    pub dedup: bool,
+    #[arg(
+        long,
+        overrides_with = "no_follow_symlinks",
+        help = "Copy the contents of symlinks inside directory entries instead of skipping them"
+    )]
+    pub follow_symlinks: bool,
+    #[arg(
+        long,
+        overrides_with = "follow_symlinks",
+        help = "Skip symlinks inside directory entries even if config.toml sets follow_symlinks"
+    )]
+    pub no_follow_symlinks: bool,
+    #[arg(
+        long,
+        short = 'v',
+        help = "Print each rsync command used for directory entries and show its output"
+    )]
+    pub verbose: bool,
+    #[arg(
+        long,
+        conflicts_with = "verbose",
+        help = "Print only section counts, warnings and errors, not a line per entry"
+    )]
+    pub summary_only: bool,
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "snapshot",
+        help = "Write a self-contained backup under DIR (same layout as ~/.mntn) instead of ~/.mntn"
+    )]
+    pub output_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        help = "Only back up config files modified within DURATION (e.g. 30m, 12h, 1d, 2w); older files keep their previous backup"
+    )]
+    pub since: Option<Duration>,
+    #[arg(
+        long,
+        conflicts_with = "output_dir",
+        help = "Stage and commit the backup in ~/.mntn (same message as sync) without pushing"
+    )]
+    pub git_commit: bool,
+    #[arg(
+        long,
+        help = "After the backup, print each config entry's live target and backup path to stdout as JSON lines; progress goes to stderr"
+    )]
+    pub print_manifest: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["snapshot", "git_commit", "print_manifest", "output_dir"],
+        help = "List what would be backed up with the size of each live target and a total, without writing anything"
+    )]
+    pub dry_run: bool,
+}
+
+/// Parse durations such as `90s`, `30m`, `12h`, `1d` or `2w`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{}' (use s, m, h, d or w)", value))?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}' (use s, m, h, d or w)", unit)),
+    };
+    Ok(Duration::from_secs(amount.saturating_mul(seconds)))
 }
 
 impl BackupArgs {
@@ -80,14 +287,79 @@ impl BackupArgs {
 pub(crate) struct RestoreArgs {
     #[arg(
         long,
+        overrides_with = "no_skip_encrypted",
         help = "Skip encrypted configs restore (will not prompt for password)"
     )]
     pub skip_encrypted: bool,
+    #[arg(
+        long,
+        overrides_with = "skip_encrypted",
+        help = "Restore encrypted configs even if config.toml sets skip_encrypted"
+    )]
+    pub no_skip_encrypted: bool,
     #[arg(
         long,
         help = "Always prompt for the encryption password instead of using the one stored in the system keychain"
     )]
     pub ask_password: bool,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Restore under this directory instead of the real targets (e.g. /home/me/.zshrc becomes DIR/home/me/.zshrc)"
+    )]
+    pub target_root: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "After restoring, delete live targets of registry entries that have no backup in any layer"
+    )]
+    pub prune: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Restore only from this layer, ignoring copies in the other one"
+    )]
+    pub layer: Option<SourceLayer>,
+    #[arg(
+        long,
+        help = "Show what would be restored (and pruned) without writing or deleting anything"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        overrides_with = "no_backup_first",
+        conflicts_with = "dry_run",
+        help = "Before restoring, copy every live target into ~/.mntn/pre-restore/<timestamp>/"
+    )]
+    pub backup_first: bool,
+    #[arg(
+        long,
+        overrides_with = "backup_first",
+        help = "Do not copy live targets first even if config.toml sets backup_first"
+    )]
+    pub no_backup_first: bool,
+    #[arg(
+        long = "exclude-entry",
+        value_name = "ID",
+        help = "Leave the registry entry with this id out of the restore (repeatable)"
+    )]
+    pub exclude_entries: Vec<String>,
+    #[arg(
+        long,
+        short = 'v',
+        help = "Print each rsync command used for directory entries and show its output"
+    )]
+    pub verbose: bool,
+    #[arg(
+        long,
+        conflicts_with = "verbose",
+        help = "Print only section counts, warnings and errors, not a line per entry"
+    )]
+    pub summary_only: bool,
+    #[arg(
+        long,
+        help = "Choose which config entries to restore from a list (labelled with the layer each backup resolves from)"
+    )]
+    pub interactive: bool,
 }
 
 impl RestoreArgs {
@@ -96,20 +368,116 @@ impl RestoreArgs {
     }
 }
 
+#[derive(Args)]
+pub(crate) struct InfoArgs {
+    #[arg(
+        long,
+        short = 'p',
+        help = "Show what a specific profile would resolve to"
+    )]
+    pub profile: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct StatusArgs {
+    #[arg(long, short = 'p', help = "Show the status for a specific profile")]
+    pub profile: Option<String>,
+    #[arg(
+        long,
+        help = "Print the status as a JSON object (versioned by its schema_version field)"
+    )]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct DiffArgs {
+    #[arg(
+        long,
+        short = 'p',
+        help = "Compare against a specific profile's backup"
+    )]
+    pub profile: Option<String>,
+}
+
+impl DiffArgs {
+    pub fn resolve_profile(&self) -> ActiveProfile {
+        ActiveProfile::resolve(self.profile.as_deref())
+    }
+}
+
+#[derive(Args)]
+pub(crate) struct EditArgs {
+    #[arg(help = "Id of the config registry entry to edit")]
+    pub id: String,
+    #[arg(
+        long,
+        short = 'p',
+        help = "Edit the copy a specific profile resolves to"
+    )]
+    pub profile: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Edit the copy in this layer, creating it from the resolved copy if it is missing"
+    )]
+    pub layer: Option<SourceLayer>,
+    #[arg(
+        long,
+        help = "After the editor exits, restore the entry to its live target"
+    )]
+    pub apply: bool,
+}
+
 #[derive(Args)]
 pub(crate) struct DoctorArgs {
     #[command(subcommand)]
     pub action: Option<DoctorActions>,
     #[arg(
         long,
+        help = "Print external tools and check results as a JSON object (versioned by its schema_version field)"
+    )]
+    pub json: bool,
+    #[arg(
+        long,
+        overrides_with = "no_skip_encrypted",
         help = "Skip encrypted configs validation (will not prompt for password)"
     )]
     pub skip_encrypted: bool,
+    #[arg(
+        long,
+        overrides_with = "skip_encrypted",
+        help = "Validate encrypted configs even if config.toml sets skip_encrypted"
+    )]
+    pub no_skip_encrypted: bool,
     #[arg(
         long,
         help = "Always prompt for the encryption password instead of using the one stored in the system keychain"
     )]
     pub ask_password: bool,
+    #[arg(
+        long,
+        overrides_with = "no_parallel",
+        help = "Run the checks concurrently (results are still printed in the usual order)"
+    )]
+    pub parallel: bool,
+    #[arg(
+        long,
+        overrides_with = "parallel",
+        help = "Run the checks one at a time even if config.toml sets parallel"
+    )]
+    pub no_parallel: bool,
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Check only this registry entry, even if it is disabled: its backup, target, and (for package entries) command"
+    )]
+    pub entry: Option<String>,
+    #[arg(
+        long,
+        conflicts_with_all = ["json", "entry"],
+        help = "Instead of running the checks, list every enabled config entry with the layer it resolves from and which layers hold a copy"
+    )]
+    pub layers: bool,
 }
 
 impl DoctorArgs {
@@ -152,6 +520,70 @@ pub(crate) struct SyncArgs {
         help = "Custom commit message; defaults to chore: sync mntn (<UTC date time>) when omitted"
     )]
     pub message: Option<String>,
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with = "message",
+        help = "Commit message with placeholders filled in: {hostname}, {date}, {profile}, {changed} (number of changed files)"
+    )]
+    pub message_template: Option<String>,
+    #[arg(
+        long,
+        overrides_with = "no_pull",
+        help = "Pull (merge) from the remote after committing and before pushing"
+    )]
+    pub pull: bool,
+    #[arg(
+        long,
+        overrides_with = "pull",
+        help = "Do not pull even if config.toml sets pull"
+    )]
+    pub no_pull: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Resolve conflicting hunks automatically when pulling: ours keeps local changes, theirs keeps the remote's"
+    )]
+    pub strategy: Option<SyncStrategy>,
+    #[arg(
+        long,
+        help = "Show the staged changes (git diff --cached --stat) and ask before committing"
+    )]
+    pub preview: bool,
+    #[arg(
+        long,
+        short = 'y',
+        requires = "preview",
+        help = "With --preview, print the changes but commit without asking"
+    )]
+    pub yes: bool,
+    #[arg(
+        long,
+        short = 'n',
+        conflicts_with = "preview",
+        help = "Fetch and show what would be committed, pulled, and pushed without changing anything"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2,
+        help = "Retry fetch, pull, and push up to N times with backoff after network errors (not after rejections or authentication failures)"
+    )]
+    pub retries: u32,
+}
+
+impl SyncArgs {
+    pub fn resolve_profile(&self) -> ActiveProfile {
+        ActiveProfile::resolve(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SyncStrategy {
+    Ours,
+    Theirs,
 }
 
 #[derive(Args)]
@@ -1,69 +1,288 @@
-use super::utils::{backup_directory, backup_file};
+use super::manifest::{ManifestEntry, sha256_file};
+use super::utils::{
+    backup_directory, backup_file, backup_glob, backup_recent_files, backup_symlink,
+    disabled_marker, disabled_summary, modified_since,
+};
+use super::{BackupOptions, EditedBackup};
+use crate::registry::EntryFilter;
 use crate::registry::config::ConfigRegistry;
-use crate::utils::display::{green, yellow};
+use crate::utils::display::{entry_status, green, status, warning, yellow};
+use crate::utils::glob::GlobPattern;
 use crate::utils::paths::get_config_registry_path;
+use crate::utils::system::run_hook;
 use anyhow::{Context, Result};
+use inquire::Select;
 use std::fs;
-use std::path::Path;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-pub fn backup_configs(configs_path: &Path) -> Result<(u32, u32)> {
+#[derive(Default)]
+pub struct ConfigBackupReport {
+    pub succeeded: u32,
+    pub skipped: u32,
+    /// Entries left alone because nothing in them changed since `--since`.
+    pub unchanged: u32,
+    /// `(source_path, target_path)` for every entry that was copied, plus
+    /// unchanged entries whose earlier backup is still in place.
+    pub backed_up: Vec<(String, PathBuf)>,
+    /// Manifest records of backup copies that were edited directly and kept,
+    /// so the next run still sees them as edited.
+    pub kept_edits: Vec<ManifestEntry>,
+}
+
+/// What happened to one entry.
+enum EntryOutcome {
+    Copied,
+    /// `since` found nothing new.
+    Unchanged,
+    /// The backup copy was edited directly and left in place.
+    KeptEdit,
+}
+
+/// How a single-file entry's backup copy differs from the last backup.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum BackupEdit {
+    /// The backup copy was edited; the live file still matches the last backup.
+    BackupOnly,
+    /// The backup copy and the live file were both changed, differently.
+    Both,
+}
+
+/// How to resolve a backup copy that was edited since the last backup.
+#[derive(Clone, Copy, PartialEq)]
+enum EditChoice {
+    Overwrite,
+    Keep,
+    RestoreEdit,
+}
+
+impl std::fmt::Display for EditChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EditChoice::Overwrite => "Overwrite the backup with the live file",
+            EditChoice::Keep => "Keep the edited backup and skip this entry",
+            EditChoice::RestoreEdit => "Copy the edited backup over the live file",
+        })
+    }
+}
+
+pub fn backup_configs(
+    configs_path: &Path,
+    filter: &EntryFilter,
+    options: &BackupOptions,
+    since: Option<SystemTime>,
+    previous: &[ManifestEntry],
+    has_profile: bool,
+) -> Result<ConfigBackupReport> {
+    let (follow_symlinks, verbose) = (options.follow_symlinks, options.verbose);
     let config_registry_path = get_config_registry_path();
     let config_registry = ConfigRegistry::load_or_create(&config_registry_path)
         .with_context(|| format!("Load config registry: {}", config_registry_path.display()))?;
 
-    let enabled_entries: Vec<_> = config_registry.get_enabled_entries().collect();
+    let entries: Vec<_> = config_registry.filtered_entries(filter).collect();
 
-    if enabled_entries.is_empty() {
-        println!("No configuration files found to backup");
-        return Ok((0, 0));
+    if entries.is_empty() {
+        status!("No configuration files found to backup");
+        return Ok(ConfigBackupReport::default());
     }
 
-    println!("   Configurations: {} entries", enabled_entries.len());
+    status!(
+        "   Configurations: {} entries{}",
+        entries.len(),
+        disabled_summary(entries.iter().filter(|(_, e)| !e.enabled).count())
+    );
 
-    let mut succeeded = 0;
-    let mut skipped = 0;
+    let mut report = ConfigBackupReport::default();
 
-    for (id, entry) in enabled_entries {
+    for (id, entry) in entries {
         let target_path = &entry.target_path;
         let backup_destination = configs_path.join(&entry.source_path);
+        // Relative paths of the files a glob entry matched.
+        let mut glob_files = Vec::new();
+
+        if entry.profile_required && !has_profile {
+            report.skipped += 1;
+            warning!(
+                "{}",
+                yellow(&format!(
+                    "     skipped {} ({}): profile_required, but no profile is active",
+                    entry.source_path, id
+                ))
+            );
+            continue;
+        }
+
+        // Like post_restore, a glob entry's hook runs once with the
+        // pattern's base directory as the target.
+        if let Some(hook) = &entry.pre_backup {
+            let hook_target = if entry.glob {
+                GlobPattern::new(target_path).base().to_path_buf()
+            } else {
+                target_path.clone()
+            };
+            match run_hook(hook, &hook_target) {
+                Ok(_) => entry_status!("       ran pre_backup hook for {}", entry.source_path),
+                Err(e) => warning!(
+                    "{}",
+                    yellow(&format!("     pre_backup hook for {} failed: {:#}", id, e))
+                ),
+            }
+        }
 
-        let entry_result: Result<()> = (|| {
+        let entry_result: Result<EntryOutcome> = (|| {
             if let Some(parent) = backup_destination.parent() {
                 fs::create_dir_all(parent).with_context(|| {
                     format!("Prepare backup path {} ({})", parent.display(), id)
                 })?;
             }
 
-            if target_path.is_dir() {
-                backup_directory(target_path, &backup_destination).with_context(|| {
+            if entry.glob {
+                let (copied, matched) = backup_glob(target_path, &backup_destination, since)
+                    .with_context(|| format!("Copy files matching {}", target_path.display()))?;
+                glob_files = matched;
+                return Ok(if copied > 0 {
+                    EntryOutcome::Copied
+                } else {
+                    EntryOutcome::Unchanged
+                });
+            }
+
+            if entry.preserve_symlink && target_path.is_symlink() {
+                backup_symlink(target_path, &backup_destination).with_context(|| {
                     format!(
-                        "Copy directory {} -> {}",
+                        "Copy symlink {} -> {}",
                         target_path.display(),
                         backup_destination.display()
                     )
-                })
+                })?;
+            } else if target_path.is_dir() {
+                if let Some(cutoff) = since {
+                    let copied = backup_recent_files(
+                        target_path,
+                        &backup_destination,
+                        cutoff,
+                        follow_symlinks,
+                    )
+                    .with_context(|| {
+                        format!(
+                            "Copy recent files {} -> {}",
+                            target_path.display(),
+                            backup_destination.display()
+                        )
+                    })?;
+                    return Ok(if copied > 0 {
+                        EntryOutcome::Copied
+                    } else {
+                        EntryOutcome::Unchanged
+                    });
+                }
+                backup_directory(target_path, &backup_destination, follow_symlinks, verbose)
+                    .with_context(|| {
+                        format!(
+                            "Copy directory {} -> {}",
+                            target_path.display(),
+                            backup_destination.display()
+                        )
+                    })?;
             } else {
+                if let Some(cutoff) = since
+                    && target_path.exists()
+                    && !modified_since(target_path, cutoff)
+                {
+                    return Ok(EntryOutcome::Unchanged);
+                }
+                let recorded = previous.iter().find(|e| e.path == entry.source_path);
+                if let Some(recorded) = recorded
+                    && let Some(edit) =
+                        backup_copy_edited(&recorded.sha256, target_path, &backup_destination)
+                {
+                    match resolve_edit(&entry.source_path, edit, options.on_edited_backup)? {
+                        EditChoice::Overwrite => {}
+                        EditChoice::Keep => return Ok(EntryOutcome::KeptEdit),
+                        EditChoice::RestoreEdit => {
+                            fs::copy(&backup_destination, target_path).with_context(|| {
+                                format!(
+                                    "Copy edited backup {} -> {}",
+                                    backup_destination.display(),
+                                    target_path.display()
+                                )
+                            })?;
+                            status!(
+                                "     applied the edited backup to {}",
+                                target_path.display()
+                            );
+                            return Ok(EntryOutcome::Copied);
+                        }
+                    }
+                }
                 backup_file(target_path, &backup_destination).with_context(|| {
                     format!(
                         "Copy file {} -> {}",
                         target_path.display(),
                         backup_destination.display()
                     )
-                })
+                })?;
             }
+            Ok(EntryOutcome::Copied)
         })();
 
+        // Glob entries are recorded file by file, since their target is a pattern.
+        let backed_up = if entry.glob {
+            let glob = GlobPattern::new(target_path);
+            glob_files
+                .iter()
+                .map(|relative| {
+                    (
+                        format!("{}/{}", entry.source_path, relative.to_string_lossy()),
+                        glob.base().join(relative),
+                    )
+                })
+                .collect()
+        } else {
+            vec![(entry.source_path.clone(), target_path.clone())]
+        };
+
         match entry_result {
-            Ok(()) => {
-                succeeded += 1;
-                println!("     {} {}", green("✔"), entry.source_path);
+            Ok(EntryOutcome::Unchanged) => {
+                report.unchanged += 1;
+                if backup_destination.exists() {
+                    report.backed_up.extend(backed_up);
+                }
+                entry_status!("     unchanged {}", entry.source_path);
+            }
+            Ok(EntryOutcome::KeptEdit) => {
+                report.skipped += 1;
+                report.kept_edits.extend(
+                    previous
+                        .iter()
+                        .filter(|e| e.path == entry.source_path)
+                        .cloned(),
+                );
+                warning!(
+                    "{}",
+                    yellow(&format!(
+                        "     kept {} ({}): the backup copy was edited since the last backup; run mntn restore to apply it",
+                        entry.source_path, id
+                    ))
+                );
+            }
+            Ok(EntryOutcome::Copied) => {
+                report.succeeded += 1;
+                report.backed_up.extend(backed_up);
+                entry_status!(
+                    "     {} {}{}",
+                    green("✔"),
+                    entry.source_path,
+                    disabled_marker(entry.enabled)
+                );
             }
             Err(e) => {
-                skipped += 1;
-                eprintln!(
+                report.skipped += 1;
+                warning!(
                     "{}",
                     yellow(&format!(
-                        "     skipped {} ({}): {}",
+                        "     skipped {} ({}): {:#}",
                         entry.source_path, id, e
                     ))
                 );
@@ -71,5 +290,148 @@ pub fn backup_configs(configs_path: &Path) -> Result<(u32, u32)> {
         }
     }
 
-    Ok((succeeded, skipped))
+    Ok(report)
+}
+
+/// Whether copying would silently throw away an edit made directly in the
+/// backup copy of a single-file entry: the backup no longer matches the hash
+/// `recorded` at the last backup, and the live file holds something else.
+/// Glob entries are copied file by file without this check.
+fn backup_copy_edited(recorded: &str, live: &Path, backup: &Path) -> Option<BackupEdit> {
+    let backup_hash = sha256_file(backup).ok()?;
+    if backup_hash == recorded {
+        return None;
+    }
+    match sha256_file(live) {
+        Ok(live_hash) if live_hash == backup_hash => None,
+        Ok(live_hash) if live_hash == recorded => Some(BackupEdit::BackupOnly),
+        _ => Some(BackupEdit::Both),
+    }
+}
+
+/// Decide what to do with an edited backup copy. With
+/// [`EditedBackup::Prompt`], ask, offering first to apply the edit when only
+/// the backup changed and to keep it when both did; without a terminal the
+/// edit is kept, so unattended backups never overwrite it.
+fn resolve_edit(source_path: &str, edit: BackupEdit, policy: EditedBackup) -> Result<EditChoice> {
+    match policy {
+        EditedBackup::Keep => return Ok(EditChoice::Keep),
+        EditedBackup::Overwrite => return Ok(EditChoice::Overwrite),
+        EditedBackup::Prompt if !std::io::stdin().is_terminal() => return Ok(EditChoice::Keep),
+        EditedBackup::Prompt => {}
+    }
+    let (message, choices) = match edit {
+        BackupEdit::BackupOnly => (
+            format!(
+                "The backup of {} was edited since the last backup; the live file is unchanged:",
+                source_path
+            ),
+            vec![
+                EditChoice::RestoreEdit,
+                EditChoice::Keep,
+                EditChoice::Overwrite,
+            ],
+        ),
+        BackupEdit::Both => (
+            format!(
+                "The backup of {} was edited since the last backup and the live file changed too:",
+                source_path
+            ),
+            vec![
+                EditChoice::Keep,
+                EditChoice::Overwrite,
+                EditChoice::RestoreEdit,
+            ],
+        ),
+    };
+    Select::new(&message, choices)
+        .prompt()
+        .context("Ask how to resolve an edited backup")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Files {
+        dir: PathBuf,
+        recorded: String,
+    }
+
+    impl Files {
+        /// A live file and its backup copy, both holding `original`, with the
+        /// hash the last backup recorded.
+        fn new(name: &str, original: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("mntn-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("live"), original).unwrap();
+            fs::write(dir.join("backup"), original).unwrap();
+            let recorded = sha256_file(&dir.join("backup")).unwrap();
+            Self { dir, recorded }
+        }
+
+        fn edit(&self, which: &str, content: &str) -> &Self {
+            fs::write(self.dir.join(which), content).unwrap();
+            self
+        }
+
+        fn check(&self) -> Option<BackupEdit> {
+            backup_copy_edited(
+                &self.recorded,
+                &self.dir.join("live"),
+                &self.dir.join("backup"),
+            )
+        }
+    }
+
+    impl Drop for Files {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn untouched_backup_is_not_an_edit() {
+        let files = Files::new("edit-none", "a");
+        assert_eq!(files.check(), None);
+        assert_eq!(files.edit("live", "b").check(), None);
+    }
+
+    #[test]
+    fn backup_edited_with_live_unchanged() {
+        let files = Files::new("edit-backup", "a");
+        assert_eq!(
+            files.edit("backup", "b").check(),
+            Some(BackupEdit::BackupOnly)
+        );
+    }
+
+    #[test]
+    fn backup_and_live_both_changed() {
+        let files = Files::new("edit-both", "a");
+        files.edit("backup", "b").edit("live", "c");
+        assert_eq!(files.check(), Some(BackupEdit::Both));
+    }
+
+    #[test]
+    fn same_change_on_both_sides_is_not_an_edit() {
+        let files = Files::new("edit-same", "a");
+        files.edit("backup", "b").edit("live", "b");
+        assert_eq!(files.check(), None);
+    }
+
+    #[test]
+    fn keep_and_overwrite_never_prompt() {
+        for edit in [BackupEdit::BackupOnly, BackupEdit::Both] {
+            assert!(matches!(
+                resolve_edit("a", edit, EditedBackup::Keep),
+                Ok(EditChoice::Keep)
+            ));
+            assert!(matches!(
+                resolve_edit("a", edit, EditedBackup::Overwrite),
+                Ok(EditChoice::Overwrite)
+            ));
+        }
+    }
 }
@@ -0,0 +1,62 @@
+use super::manifest::{ManifestEntry, load_manifest, sha256_file};
+use crate::utils::display::{status, yellow};
+use crate::utils::format::bytes_to_human_readable;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Report files in the profile layer that are byte-for-byte identical to the
+/// common layer copy they override. Such copies are redundant: removing them
+/// from the profile layer restores the same content from common.
+///
+/// Hashes come from the common layer's manifest when it has one, and are
+/// computed from the common files otherwise.
+pub(crate) fn report_layer_duplicates(
+    profile_entries: &[ManifestEntry],
+    common_path: &Path,
+) -> Result<()> {
+    let common_hashes: Option<HashMap<String, String>> =
+        load_manifest(common_path)?.map(|manifest| {
+            manifest
+                .files
+                .into_iter()
+                .map(|entry| (entry.path, entry.sha256))
+                .collect()
+        });
+
+    let duplicates: Vec<&ManifestEntry> = profile_entries
+        .iter()
+        .filter(|entry| {
+            let common_hash = match &common_hashes {
+                Some(hashes) => hashes.get(&entry.path).cloned(),
+                None => sha256_file(&common_path.join(&entry.path)).ok(),
+            };
+            common_hash.as_deref() == Some(entry.sha256.as_str())
+        })
+        .collect();
+
+    if duplicates.is_empty() {
+        status!("   Duplicates across layers: none");
+        return Ok(());
+    }
+
+    let total_bytes: u64 = duplicates.iter().map(|entry| entry.size).sum();
+    status!(
+        "   Duplicates across layers: {} files ({}) identical to the common layer",
+        duplicates.len(),
+        bytes_to_human_readable(total_bytes, true)
+    );
+    for entry in &duplicates {
+        status!(
+            "{}",
+            yellow(&format!(
+                "     = {} ({})",
+                entry.path,
+                bytes_to_human_readable(entry.size, true)
+            ))
+        );
+    }
+    status!("   Remove these from the profile layer to fall back to the common copies");
+
+    Ok(())
+}
@@ -1,50 +1,69 @@
+use super::utils::{disabled_marker, disabled_summary};
 use crate::encryption::{
-    create_temp_path, encrypt_file, resolve_encryption_password, write_entries_tar,
+    create_temp_path, decrypt_file, encrypt_file, load_tar_member_map, resolve_encryption_password,
+    write_entries_tar,
 };
+use crate::registry::EntryFilter;
 use crate::registry::encrypted::EncryptedRegistry;
 use crate::utils::{
-    display::{green, yellow},
+    display::{entry_status, green, status, warning, yellow},
     paths::{ENCRYPTED_BUNDLE_FILE, get_encrypted_registry_path},
 };
 use age::secrecy::SecretString;
 use anyhow::{Context, Result};
+use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
 
 pub fn backup_encrypted_configs(
     encrypted_backup_path: &Path,
     ask_password: bool,
+    filter: &EntryFilter,
 ) -> Result<(u32, u32)> {
     let password = resolve_encryption_password(ask_password, true)
         .context("Prompt for encryption password before encrypted backup")?;
 
-    backup_encrypted_configs_with_password(encrypted_backup_path, &password)
+    backup_encrypted_configs_with_password(encrypted_backup_path, &password, filter)
 }
 
 fn backup_encrypted_configs_with_password(
     encrypted_backup_path: &Path,
     password: &SecretString,
+    filter: &EntryFilter,
 ) -> Result<(u32, u32)> {
     let registry_path = get_encrypted_registry_path();
     let encrypted_registry = EncryptedRegistry::load_or_create(&registry_path)
         .with_context(|| format!("Load encrypted registry: {}", registry_path.display()))?;
 
-    let enabled_entries: Vec<_> = encrypted_registry.get_enabled_entries().collect();
+    backup_encrypted_entries(&encrypted_registry, encrypted_backup_path, password, filter)
+}
+
+fn backup_encrypted_entries(
+    encrypted_registry: &EncryptedRegistry,
+    encrypted_backup_path: &Path,
+    password: &SecretString,
+    filter: &EntryFilter,
+) -> Result<(u32, u32)> {
+    let entries: Vec<_> = encrypted_registry.filtered_entries(filter).collect();
 
-    if enabled_entries.is_empty() {
-        println!("No encrypted configuration files found to backup");
+    if entries.is_empty() {
+        status!("No encrypted configuration files found to backup");
         return Ok((0, 0));
     }
 
-    println!("   Encrypted configs: {} entries", enabled_entries.len());
+    status!(
+        "   Encrypted configs: {} entries{}",
+        entries.len(),
+        disabled_summary(entries.iter().filter(|(_, e)| !e.enabled).count())
+    );
 
     let mut skipped: u32 = 0;
-    let mut to_archive: Vec<(String, std::path::PathBuf)> = Vec::new();
+    let mut to_archive: Vec<(String, std::path::PathBuf, bool)> = Vec::new();
 
-    for (id, entry) in enabled_entries {
+    for (id, entry) in entries {
         if !entry.target_path.exists() {
             skipped += 1;
-            println!(
+            warning!(
                 "{}",
                 yellow(&format!(
                     "     skipped missing target {} ({})",
@@ -57,7 +76,7 @@ fn backup_encrypted_configs_with_password(
 
         if entry.target_path.is_dir() {
             skipped += 1;
-            println!(
+            warning!(
                 "{}",
                 yellow(&format!(
                     "     skipped directory {} ({})",
@@ -68,27 +87,41 @@ fn backup_encrypted_configs_with_password(
             continue;
         }
 
-        to_archive.push((entry.source_path.clone(), entry.target_path.clone()));
+        to_archive.push((
+            entry.source_path.clone(),
+            entry.target_path.clone(),
+            entry.enabled,
+        ));
     }
 
     to_archive.sort_by(|a, b| a.0.cmp(&b.0));
 
     let bundle_destination = encrypted_backup_path.join(ENCRYPTED_BUNDLE_FILE);
-
-    if to_archive.is_empty() {
+    let kept = kept_bundle_members(
+        encrypted_registry,
+        &to_archive,
+        &bundle_destination,
+        password,
+    )?;
+
+    if to_archive.is_empty() && kept.is_empty() {
         let _ = fs::remove_file(&bundle_destination);
         return Ok((0, skipped));
     }
 
     let tar_refs: Vec<(&str, &Path)> = to_archive
         .iter()
-        .map(|(source, target)| (source.as_str(), target.as_path()))
+        .map(|(source, target, _)| (source.as_str(), target.as_path()))
+        .collect();
+    let kept_refs: Vec<(&str, &[u8])> = kept
+        .iter()
+        .map(|(source, data)| (source.as_str(), data.as_slice()))
         .collect();
 
     let tar_temp = create_temp_path("enc-bundle-tar").context("Create temporary tar path")?;
 
     let backup_result: Result<()> = (|| {
-        write_entries_tar(&tar_temp, &tar_refs)?;
+        write_entries_tar(&tar_temp, &tar_refs, &kept_refs)?;
         encrypt_file(&tar_temp, &bundle_destination, password).context("Encrypt config bundle")?;
         Ok(())
     })();
@@ -98,9 +131,116 @@ fn backup_encrypted_configs_with_password(
     backup_result?;
 
     let succeeded = to_archive.len();
-    for (source_path, _) in &to_archive {
-        println!("     {} {}", green("✔"), source_path);
+    for (source_path, _, enabled) in &to_archive {
+        entry_status!(
+            "     {} {}{}",
+            green("✔"),
+            source_path,
+            disabled_marker(*enabled)
+        );
     }
 
     Ok((succeeded as u32, skipped))
 }
+
+/// Members of the existing bundle that belong to registry entries this run
+/// does not archive (left out with `--exclude-entry` or `--interactive`, or
+/// skipped), so rewriting the bundle never drops their last good backup.
+fn kept_bundle_members(
+    encrypted_registry: &EncryptedRegistry,
+    to_archive: &[(String, std::path::PathBuf, bool)],
+    bundle: &Path,
+    password: &SecretString,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let archived: HashSet<&str> = to_archive
+        .iter()
+        .map(|(source, _, _)| source.as_str())
+        .collect();
+    let left_out: Vec<&str> = encrypted_registry
+        .entries
+        .values()
+        .map(|entry| entry.source_path.as_str())
+        .filter(|source| !archived.contains(source))
+        .collect();
+    if left_out.is_empty() || !bundle.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let tar_temp = create_temp_path("enc-bundle-old").context("Create temporary tar path")?;
+    let members =
+        decrypt_file(bundle, &tar_temp, password).and_then(|()| load_tar_member_map(&tar_temp));
+    let _ = fs::remove_file(&tar_temp);
+    let mut members = members.with_context(|| {
+        format!(
+            "Read {} to keep the entries this run leaves out",
+            bundle.display()
+        )
+    })?;
+
+    let mut kept: Vec<(String, Vec<u8>)> = left_out
+        .into_iter()
+        .filter_map(|source| members.remove_entry(source))
+        .collect();
+    kept.sort();
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::REGISTRY_VERSION;
+    use crate::registry::encrypted::EncryptedRegistryEntry;
+
+    fn entry(source_path: &str, target_path: &Path) -> EncryptedRegistryEntry {
+        EncryptedRegistryEntry {
+            name: source_path.to_string(),
+            enabled: true,
+            source_path: source_path.to_string(),
+            target_path: target_path.to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn excluded_entries_keep_their_bundle_member() {
+        let dir = std::env::temp_dir().join(format!("mntn-enc-keep-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (ssh, aws) = (dir.join("ssh_config"), dir.join("aws_credentials"));
+        fs::write(&ssh, "Host *\n").unwrap();
+        fs::write(&aws, "[default]\n").unwrap();
+        let registry = EncryptedRegistry {
+            version: REGISTRY_VERSION.to_string(),
+            entries: [
+                ("ssh".to_string(), entry("ssh/config", &ssh)),
+                ("aws".to_string(), entry("aws/credentials", &aws)),
+            ]
+            .into(),
+            invalid_entries: Default::default(),
+        };
+        let password = SecretString::from("test password".to_string());
+        let backup_path = dir.join("backup");
+
+        let all = EntryFilter::default();
+        assert_eq!(
+            backup_encrypted_entries(&registry, &backup_path, &password, &all).unwrap(),
+            (2, 0)
+        );
+        fs::write(&ssh, "Host changed\n").unwrap();
+        let without_aws = EntryFilter {
+            excluded: vec!["aws".to_string()],
+            ..EntryFilter::default()
+        };
+        assert_eq!(
+            backup_encrypted_entries(&registry, &backup_path, &password, &without_aws).unwrap(),
+            (1, 0)
+        );
+
+        let tar = dir.join("bundle.tar");
+        decrypt_file(&backup_path.join(ENCRYPTED_BUNDLE_FILE), &tar, &password).unwrap();
+        let members = load_tar_member_map(&tar).unwrap();
+        assert_eq!(members["aws/credentials"], b"[default]\n");
+        assert_eq!(members["ssh/config"], b"Host changed\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
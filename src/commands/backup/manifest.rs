@@ -0,0 +1,162 @@
+use crate::utils::filesystem::mark_dir_visited;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+pub(crate) const MANIFEST_FILE: &str = "manifest.json";
+const MANIFEST_VERSION: &str = "1.0.0";
+
+/// Record of every file written by a config backup, stored as `manifest.json`
+/// in the layer directory so later runs can compare without re-reading sources.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BackupManifest {
+    pub version: String,
+    pub created_at: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ManifestEntry {
+    /// Path relative to the layer directory (the entry's `source_path`, plus
+    /// the file's relative path for directory entries).
+    pub path: String,
+    pub size: u64,
+    /// Modification time of the live file, in seconds since the Unix epoch.
+    pub modified: u64,
+    pub sha256: String,
+}
+
+/// Hash and describe each backed-up `(source_path, target_path)` pair. Directory
+/// entries contribute one manifest entry per regular file; symlinks inside
+/// directories are skipped unless `follow_symlinks` is set, matching how
+/// directory copies treat them.
+pub(crate) fn collect_manifest_entries(
+    backed_up: &[(String, PathBuf)],
+    follow_symlinks: bool,
+) -> Vec<ManifestEntry> {
+    let mut entries = Vec::new();
+    for (source_path, target_path) in backed_up {
+        let mut visited = HashSet::new();
+        collect_path(
+            source_path,
+            target_path,
+            true,
+            follow_symlinks,
+            &mut visited,
+            &mut entries,
+        );
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+fn collect_path(
+    relative: &str,
+    path: &Path,
+    is_root: bool,
+    follow_symlinks: bool,
+    visited: &mut HashSet<PathBuf>,
+    entries: &mut Vec<ManifestEntry>,
+) {
+    let metadata = if is_root || follow_symlinks {
+        fs::metadata(path)
+    } else {
+        fs::symlink_metadata(path)
+    };
+    let Ok(metadata) = metadata else {
+        return;
+    };
+
+    if metadata.is_dir() {
+        if !mark_dir_visited(visited, path) {
+            return;
+        }
+        let Ok(children) = fs::read_dir(path) else {
+            return;
+        };
+        for child in children.flatten() {
+            let name = child.file_name().to_string_lossy().into_owned();
+            collect_path(
+                &format!("{}/{}", relative, name),
+                &child.path(),
+                false,
+                follow_symlinks,
+                visited,
+                entries,
+            );
+        }
+        return;
+    }
+
+    if !metadata.is_file() {
+        return;
+    }
+
+    let Ok(sha256) = sha256_file(path) else {
+        return;
+    };
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    entries.push(ManifestEntry {
+        path: relative.to_string(),
+        size: metadata.len(),
+        modified,
+        sha256,
+    });
+}
+
+pub(crate) fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Read the manifest of a layer directory, or `None` if it has not been written yet.
+pub(crate) fn load_manifest(dir: &Path) -> Result<Option<BackupManifest>> {
+    let path = dir.join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Read manifest {}", path.display()))?;
+    let manifest = serde_json::from_str(&content)
+        .with_context(|| format!("Parse manifest {}", path.display()))?;
+    Ok(Some(manifest))
+}
+
+pub(crate) fn write_manifest(dir: &Path, entries: Vec<ManifestEntry>) -> Result<()> {
+    let manifest = BackupManifest {
+        version: MANIFEST_VERSION.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        files: entries,
+    };
+
+    let path = dir.join(MANIFEST_FILE);
+    let content = serde_json::to_string_pretty(&manifest)?;
+    fs::write(&path, content).with_context(|| format!("Write manifest {}", path.display()))?;
+    Ok(())
+}
@@ -1,81 +1,471 @@
 use crate::commands::core::Command;
+use crate::commands::{git, picker};
 use crate::profiles::ActiveProfile;
-use crate::utils::paths::get_mntn_dir;
+use crate::registry::EntryFilter;
+use crate::utils::display::{
+    Progress, hide_entry_lines, print_progress, send_status_to_stderr, status, with_progress,
+};
+use crate::utils::paths::{get_common_path, get_mntn_dir};
+use anyhow::bail;
+use std::cell::RefCell;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 
 mod config;
+mod dedup;
 mod encrypted;
+pub(crate) mod manifest;
 mod package;
+mod package_list;
+mod plan;
+mod snapshot;
 mod utils;
 
-struct BackupTask {
-    profile: ActiveProfile,
-    skip_encrypted: bool,
-    ask_password: bool,
+/// What a [`backup`] run does. `Default` matches `mntn backup` with no flags,
+/// except that edited backup copies are kept instead of asking.
+#[derive(Debug, Clone)]
+pub struct BackupOptions {
+    /// Leave the encrypted bundle alone (no password prompt).
+    pub skip_encrypted: bool,
+    /// Prompt for the encryption password instead of using the keychain.
+    pub ask_password: bool,
+    /// Copy the finished backup into a snapshot, keeping this many.
+    pub snapshot: Option<usize>,
+    /// Write `manifest.json` (sizes, mtimes, SHA-256) into the backup layer.
+    pub write_manifest: bool,
+    /// Back up disabled registry entries too.
+    pub include_disabled: bool,
+    /// Registry entry ids to leave out of this run.
+    pub exclude_entries: Vec<String>,
+    /// Config entry ids to leave out of this run, without touching package
+    /// and encrypted entries (what `--interactive` leaves unchecked).
+    pub exclude_config_entries: Vec<String>,
+    /// Report profile-layer files identical to their common-layer copies.
+    pub dedup: bool,
+    /// Copy what symlinks inside directory entries point to.
+    pub follow_symlinks: bool,
+    /// Print each rsync command and let its output through.
+    pub verbose: bool,
+    /// Package managers to run at once (at least 1).
+    pub jobs: usize,
+    /// Leave out package managers whose command is not installed, without
+    /// a warning for each.
+    pub ignore_missing_commands: bool,
+    /// Write a self-contained backup here instead of `~/.mntn`. Cannot be
+    /// combined with `snapshot` or `git_commit`, which work on `~/.mntn`.
+    pub output_dir: Option<PathBuf>,
+    /// Only copy config files modified within this window.
+    pub since: Option<Duration>,
+    /// Commit the backup in `~/.mntn` afterwards, without pushing.
+    pub git_commit: bool,
+    /// What to do with a backup copy that was edited directly in `~/.mntn`
+    /// since the last backup.
+    pub on_edited_backup: EditedBackup,
 }
 
-impl BackupTask {
-    fn new(profile: ActiveProfile, skip_encrypted: bool, ask_password: bool) -> Self {
+/// How a [`backup`] run treats a file entry whose backup copy was edited
+/// directly since the last backup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EditedBackup {
+    /// Leave the edited copy in place and skip the entry.
+    #[default]
+    Keep,
+    /// Replace the edited copy with the live file.
+    Overwrite,
+    /// Ask on the terminal, as `mntn backup` does; keeps the edit when stdin
+    /// is not a terminal.
+    Prompt,
+}
+
+impl BackupOptions {
+    /// Reject combinations the CLI refuses to parse: snapshots and commits
+    /// are of `~/.mntn`, which a run with `output_dir` leaves alone.
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.output_dir.is_some() && self.snapshot.is_some() {
+            bail!("output_dir cannot be combined with snapshot");
+        }
+        if self.output_dir.is_some() && self.git_commit {
+            bail!("output_dir cannot be combined with git_commit");
+        }
+        Ok(())
+    }
+
+    /// The package and encrypted entries this run backs up.
+    fn filter(&self) -> EntryFilter {
+        EntryFilter {
+            include_disabled: self.include_disabled,
+            excluded: self.exclude_entries.clone(),
+            ..EntryFilter::default()
+        }
+    }
+
+    /// The config entries this run backs up.
+    fn config_filter(&self) -> EntryFilter {
+        let mut filter = self.filter();
+        filter
+            .excluded
+            .extend(self.exclude_config_entries.iter().cloned());
+        filter
+    }
+}
+
+impl Default for BackupOptions {
+    fn default() -> Self {
         Self {
-            profile,
-            skip_encrypted,
-            ask_password,
+            skip_encrypted: false,
+            ask_password: false,
+            snapshot: None,
+            write_manifest: true,
+            include_disabled: false,
+            exclude_entries: Vec::new(),
+            exclude_config_entries: Vec::new(),
+            dedup: false,
+            follow_symlinks: false,
+            verbose: false,
+            jobs: default_jobs(),
+            ignore_missing_commands: false,
+            output_dir: None,
+            since: None,
+            git_commit: false,
+            on_edited_backup: EditedBackup::Keep,
         }
     }
 }
 
-impl Command for BackupTask {
-    fn name(&self) -> &str {
-        "Backup"
+/// Package managers are mostly network- and disk-bound, so a handful at once
+/// is enough even on machines with many cores.
+fn default_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(4)
+}
+
+/// Entry counts from a [`backup`] run.
+#[derive(Debug, Clone, Default)]
+pub struct BackupReport {
+    pub configs_succeeded: u32,
+    pub configs_skipped: u32,
+    /// Config entries left alone because nothing changed within `since`.
+    pub configs_unchanged: u32,
+    pub packages_succeeded: u32,
+    pub packages_skipped: u32,
+    pub encrypted_succeeded: u32,
+    pub encrypted_skipped: u32,
+    /// `(live target, backup copy)` for every config entry whose backup is in
+    /// place after the run, including entries left unchanged by `since`.
+    pub backed_up: Vec<(PathBuf, PathBuf)>,
+    /// Every progress line of the run, in order. Left empty by
+    /// [`backup_with_progress`], which hands them out as they happen.
+    pub progress: Vec<Progress>,
+}
+
+/// Back up configs, package lists and encrypted configs for `profile`, the
+/// same way `mntn backup` does. Nothing is printed; progress is collected in
+/// [`BackupReport::progress`]. Encrypted configs still prompt for the password
+/// when none is stored in the keychain, unless `skip_encrypted` is set.
+pub fn backup(profile: &ActiveProfile, options: &BackupOptions) -> anyhow::Result<BackupReport> {
+    let progress = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&progress);
+    let mut report = backup_with_progress(profile, options, move |line| {
+        sink.borrow_mut().push(line.clone())
+    })?;
+    report.progress = progress.take();
+    Ok(report)
+}
+
+/// [`backup`], calling `on_progress` with each progress line as the run goes
+/// instead of collecting them.
+pub fn backup_with_progress(
+    profile: &ActiveProfile,
+    options: &BackupOptions,
+    on_progress: impl Fn(&Progress) + 'static,
+) -> anyhow::Result<BackupReport> {
+    options.validate()?;
+    with_progress(on_progress, || BackupRun { profile, options }.run())
+}
+
+struct BackupRun<'a> {
+    profile: &'a ActiveProfile,
+    options: &'a BackupOptions,
+}
+
+impl BackupRun<'_> {
+    /// Where a path inside `~/.mntn` is written for this run. With
+    /// `--output-dir`, the same layout is recreated under that directory.
+    fn output_path(&self, path: &Path) -> PathBuf {
+        match &self.options.output_dir {
+            Some(output_dir) => {
+                let mntn_dir = get_mntn_dir();
+                output_dir.join(path.strip_prefix(&mntn_dir).unwrap_or(path))
+            }
+            None => path.to_path_buf(),
+        }
     }
 
-    fn execute(&mut self) -> anyhow::Result<()> {
-        let mntn_dir = get_mntn_dir();
-        crate::commands::git::init_repo_if_missing(&mntn_dir)?;
+    fn run(&self) -> anyhow::Result<BackupReport> {
+        let options = self.options;
+        let mut report = BackupReport::default();
+
+        if options.output_dir.is_none() {
+            git::init_repo_if_missing(&get_mntn_dir())?;
+        }
 
-        let backup_path = self.profile.get_backup_path();
+        let backup_path = self.output_path(&self.profile.get_backup_path());
         fs::create_dir_all(&backup_path)?;
 
-        println!("Backing up...");
-        println!("   Target: {}", self.profile);
+        status!("Backing up...");
+        status!("   Target: {}", self.profile);
+        if let Some(output_dir) = &options.output_dir {
+            status!("   Output directory: {}", output_dir.display());
+        }
+        if options.include_disabled {
+            status!("   Including disabled registry entries");
+        }
+        if !options.exclude_entries.is_empty() {
+            status!("   Excluding: {}", options.exclude_entries.join(", "));
+        }
+        let filter = options.filter();
+        let config_filter = options.config_filter();
+        if let Some(window) = options.since {
+            status!(
+                "   Only configs modified in the last {}",
+                crate::utils::format::duration_to_human_readable(window)
+            );
+        }
 
-        let packages_path = crate::utils::paths::get_packages_path();
+        let packages_path = self.output_path(&crate::utils::paths::get_packages_path());
         fs::create_dir_all(&packages_path)?;
 
-        let (config_success, config_skipped) = config::backup_configs(&backup_path)?;
-        println!(
-            "   Configurations completed: {} succeeded, {} skipped",
-            config_success, config_skipped
-        );
+        let cutoff = options.since.map(|window| {
+            SystemTime::now()
+                .checked_sub(window)
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        });
+        // Edits made directly to backup copies can only be told apart from
+        // stale hashes while the manifest is kept up to date.
+        let previous = if options.write_manifest {
+            manifest::load_manifest(&backup_path)
+                .ok()
+                .flatten()
+                .map(|manifest| manifest.files)
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let config_report = config::backup_configs(
+            &backup_path,
+            &config_filter,
+            options,
+            cutoff,
+            &previous,
+            self.profile.name.is_some(),
+        )?;
+        report.configs_succeeded = config_report.succeeded;
+        report.configs_skipped = config_report.skipped;
+        report.configs_unchanged = config_report.unchanged;
+        report.backed_up = config_report
+            .backed_up
+            .iter()
+            .map(|(source_path, target_path)| (target_path.clone(), backup_path.join(source_path)))
+            .collect();
+        if cutoff.is_some() {
+            status!(
+                "   Configurations completed: {} succeeded, {} skipped, {} unchanged",
+                config_report.succeeded,
+                config_report.skipped,
+                config_report.unchanged
+            );
+        } else {
+            status!(
+                "   Configurations completed: {} succeeded, {} skipped",
+                config_report.succeeded,
+                config_report.skipped
+            );
+        }
+
+        if options.write_manifest {
+            let mut entries = manifest::collect_manifest_entries(
+                &config_report.backed_up,
+                options.follow_symlinks,
+            );
+            if options.dedup {
+                if self.profile.name.is_some() {
+                    let common_path = self.output_path(&get_common_path());
+                    dedup::report_layer_duplicates(&entries, &common_path)?;
+                } else {
+                    status!("   Duplicates across layers: skipped (no profile active)");
+                }
+            }
+            entries.extend(config_report.kept_edits.iter().cloned());
+            entries.sort_by(|a, b| a.path.cmp(&b.path));
+            let file_count = entries.len();
+            manifest::write_manifest(&backup_path, entries)?;
+            status!("   Manifest written: {} files", file_count);
+        }
 
-        let (package_success, package_skipped) = package::backup_packages(&packages_path)?;
-        println!(
+        let (package_success, package_skipped) = package::backup_packages(
+            &packages_path,
+            &filter,
+            options.jobs,
+            options.ignore_missing_commands,
+        )?;
+        report.packages_succeeded = package_success;
+        report.packages_skipped = package_skipped;
+        status!(
             "   Package managers completed: {} succeeded, {} skipped",
-            package_success, package_skipped
+            package_success,
+            package_skipped
         );
 
-        if !self.skip_encrypted {
-            let encrypted_backup_path = self.profile.get_encrypted_backup_path();
+        if !options.skip_encrypted {
+            let encrypted_backup_path = self.output_path(&self.profile.get_encrypted_backup_path());
             fs::create_dir_all(&encrypted_backup_path)?;
-            let (encrypted_success, encrypted_skipped) =
-                encrypted::backup_encrypted_configs(&encrypted_backup_path, self.ask_password)?;
-            println!(
+            let (encrypted_success, encrypted_skipped) = encrypted::backup_encrypted_configs(
+                &encrypted_backup_path,
+                options.ask_password,
+                &filter,
+            )?;
+            report.encrypted_succeeded = encrypted_success;
+            report.encrypted_skipped = encrypted_skipped;
+            status!(
                 "   Encrypted configs completed: {} succeeded, {} skipped",
-                encrypted_success, encrypted_skipped
+                encrypted_success,
+                encrypted_skipped
             );
         }
 
+        if let Some(keep) = options.snapshot {
+            snapshot::create_snapshot(keep)?;
+        }
+
+        if options.git_commit {
+            let mntn_dir = get_mntn_dir();
+            if git::stage_all(&mntn_dir)? {
+                let message = git::commit_staged(&mntn_dir, None)?;
+                status!("   Committed: {}", message);
+            } else {
+                status!("   Nothing to commit");
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+struct BackupTask {
+    profile: ActiveProfile,
+    options: BackupOptions,
+    interactive: bool,
+    print_manifest: bool,
+    summary_only: bool,
+    dry_run: bool,
+}
+
+impl BackupTask {
+    fn from_args(args: crate::cli::BackupArgs) -> Self {
+        Self {
+            profile: args.resolve_profile(),
+            options: BackupOptions {
+                skip_encrypted: args.skip_encrypted,
+                ask_password: args.ask_password,
+                snapshot: args
+                    .snapshot
+                    .then_some(args.keep_snapshots.unwrap_or(5) as usize),
+                write_manifest: !args.no_manifest,
+                include_disabled: args.include_disabled,
+                exclude_entries: args.exclude_entries,
+                exclude_config_entries: Vec::new(),
+                dedup: args.dedup,
+                follow_symlinks: args.follow_symlinks,
+                verbose: args.verbose,
+                jobs: args.jobs.map_or_else(default_jobs, |jobs| jobs as usize),
+                ignore_missing_commands: args.ignore_missing_commands,
+                output_dir: args.output_dir,
+                since: args.since,
+                git_commit: args.git_commit,
+                on_edited_backup: EditedBackup::Prompt,
+            },
+            interactive: args.interactive,
+            print_manifest: args.print_manifest,
+            summary_only: args.summary_only,
+            dry_run: args.dry_run,
+        }
+    }
+}
+
+impl Command for BackupTask {
+    fn name(&self) -> &str {
+        "Backup"
+    }
+
+    fn execute(&mut self) -> anyhow::Result<()> {
+        if self.print_manifest {
+            send_status_to_stderr();
+        }
+        if self.summary_only {
+            hide_entry_lines();
+        }
+        if self.interactive {
+            let unselected = picker::pick_config_entries(
+                &self.profile,
+                &self.options.config_filter(),
+                None,
+                "back up",
+            )?;
+            self.options.exclude_config_entries.extend(unselected);
+        }
+        if self.dry_run {
+            status!("Backup plan for {}", self.profile);
+            return plan::print_backup_plan(
+                &self.options.config_filter(),
+                &self.options.filter(),
+                self.options.follow_symlinks,
+                self.options.skip_encrypted,
+                self.options.ignore_missing_commands,
+            );
+        }
+        let report = backup_with_progress(&self.profile, &self.options, print_progress)?;
+
+        if self.print_manifest {
+            // One JSON object per line, so the list can be streamed into
+            // other tools while the progress log stays on stderr.
+            for (target, backup) in &report.backed_up {
+                let line = serde_json::json!({
+                    "target": target.to_string_lossy(),
+                    "backup": backup.to_string_lossy(),
+                });
+                println!("{}", line);
+            }
+        }
         Ok(())
     }
 }
 
-pub(crate) fn run(args: crate::cli::BackupArgs) {
-    use crate::commands::core::CommandExecutor;
+pub(crate) fn task(args: crate::cli::BackupArgs) -> Box<dyn Command> {
+    Box::new(BackupTask::from_args(args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let profile = args.resolve_profile();
-    CommandExecutor::run(&mut BackupTask::new(
-        profile,
-        args.skip_encrypted,
-        args.ask_password,
-    ));
+    #[test]
+    fn output_dir_rejects_snapshot_and_git_commit() {
+        let output_dir = Some(std::env::temp_dir().join("mntn-backup-out"));
+        let snapshot = BackupOptions {
+            output_dir: output_dir.clone(),
+            snapshot: Some(5),
+            ..BackupOptions::default()
+        };
+        let git_commit = BackupOptions {
+            output_dir,
+            git_commit: true,
+            ..BackupOptions::default()
+        };
+        assert!(snapshot.validate().is_err());
+        assert!(git_commit.validate().is_err());
+        assert!(BackupOptions::default().validate().is_ok());
+    }
 }
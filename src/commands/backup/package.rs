@@ -1,40 +1,89 @@
-use crate::registry::package::{PackageRegistry, PackageRegistryEntry};
-use crate::utils::display::{green, yellow};
+use super::package_list::{normalize_lines, parse_package_list};
+use super::utils::{disabled_marker, disabled_summary};
+use crate::registry::EntryFilter;
+use crate::registry::package::{PackageOutputFormat, PackageRegistry, PackageRegistryEntry};
+use crate::utils::display::{entry_status, green, status, warning, yellow};
 use crate::utils::paths::get_package_registry_path;
-use crate::utils::system::{run_cmd, strip_ansi_codes};
+use crate::utils::system::{is_command_available, run_cmd_with_env, strip_ansi_codes};
 use anyhow::{Context, Result};
 use std::fs;
 use std::io::Write;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 
-pub fn backup_packages(packages_path: &Path) -> Result<(u32, u32)> {
+/// Export package lists, running at most `jobs` package managers at once.
+/// With `ignore_missing`, entries whose command is not installed are left
+/// out up front instead of each failing with a warning.
+pub fn backup_packages(
+    packages_path: &Path,
+    filter: &EntryFilter,
+    jobs: usize,
+    ignore_missing: bool,
+) -> Result<(u32, u32)> {
     let package_registry_path = get_package_registry_path();
     let package_registry = PackageRegistry::load_or_create(&package_registry_path)
         .with_context(|| format!("Load package registry: {}", package_registry_path.display()))?;
 
     let current_platform = PackageRegistry::get_current_platform();
-    let compatible_entries: Vec<_> = package_registry
-        .get_platform_compatible_entries(&current_platform)
+    let mut compatible_entries: Vec<_> = package_registry
+        .get_platform_compatible_entries(&current_platform, filter)
         .collect();
 
+    if ignore_missing {
+        let (installed, missing): (Vec<_>, Vec<_>) = compatible_entries
+            .into_iter()
+            .partition(|(_, entry)| is_command_available(&entry.command));
+        compatible_entries = installed;
+        if !missing.is_empty() {
+            let names: Vec<&str> = missing.iter().map(|(id, _)| id.as_str()).collect();
+            status!("   Not installed, ignored: {}", names.join(", "));
+        }
+    }
+
     if compatible_entries.is_empty() {
-        println!("No package managers found to backup");
+        status!("No package managers found to backup");
         return Ok((0, 0));
     }
 
-    println!("   Package managers: {} entries", compatible_entries.len());
-
+    status!(
+        "   Package managers: {} entries{}",
+        compatible_entries.len(),
+        disabled_summary(
+            compatible_entries
+                .iter()
+                .filter(|(_, e)| !e.enabled)
+                .count()
+        )
+    );
+
+    // Workers pull the next entry as they finish, so one slow package
+    // manager does not hold up a whole batch.
+    let workers = jobs.clamp(1, compatible_entries.len());
+    let next = AtomicUsize::new(0);
     let mut outcomes: Vec<PackageBackupOutcome> = thread::scope(|s| {
-        let mut handles = Vec::with_capacity(compatible_entries.len());
-        for (id, entry) in compatible_entries {
-            let id = id.clone();
-            let entry = entry.clone();
-            handles.push(s.spawn(|| run_single_package_backup(packages_path, id, entry)));
-        }
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut outcomes = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((id, entry)) = compatible_entries.get(index) else {
+                            break;
+                        };
+                        outcomes.push(run_single_package_backup(
+                            packages_path,
+                            (*id).clone(),
+                            (*entry).clone(),
+                        ));
+                    }
+                    outcomes
+                })
+            })
+            .collect();
         handles
             .into_iter()
-            .map(|h| h.join().expect("package backup thread panicked"))
+            .flat_map(|h| h.join().expect("package backup thread panicked"))
             .collect()
     });
 
@@ -45,15 +94,38 @@ pub fn backup_packages(packages_path: &Path) -> Result<(u32, u32)> {
 
     for o in outcomes {
         match o.result {
-            Ok(()) => {
+            Ok(written_as_text) => {
                 succeeded += 1;
-                println!("     {} {}", green("✔"), o.output_file);
+                entry_status!(
+                    "     {} {}{}",
+                    green("✔"),
+                    o.output_file,
+                    disabled_marker(o.enabled)
+                );
+                if written_as_text {
+                    warning!(
+                        "{}",
+                        yellow(&format!(
+                            "     {} ({}): output of {} not recognised, kept as text",
+                            o.output_file, o.id, o.command
+                        ))
+                    );
+                }
             }
             Err(e) => {
                 skipped += 1;
-                eprintln!(
+                // Lists are only replaced on success, so an earlier one survives.
+                let kept = if packages_path.join(&o.output_file).exists() {
+                    "; kept the previous list"
+                } else {
+                    ""
+                };
+                warning!(
                     "{}",
-                    yellow(&format!("     skipped {} ({}): {}", o.output_file, o.id, e))
+                    yellow(&format!(
+                        "     skipped {} ({}): {:#}{}",
+                        o.output_file, o.id, e, kept
+                    ))
                 );
             }
         }
@@ -65,7 +137,10 @@ pub fn backup_packages(packages_path: &Path) -> Result<(u32, u32)> {
 struct PackageBackupOutcome {
     id: String,
     output_file: String,
-    result: Result<()>,
+    command: String,
+    enabled: bool,
+    /// `true` when a `json` entry had to fall back to the raw output.
+    result: Result<bool>,
 }
 
 fn run_single_package_backup(
@@ -74,13 +149,28 @@ fn run_single_package_backup(
     entry: PackageRegistryEntry,
 ) -> PackageBackupOutcome {
     let output_file = entry.output_file.clone();
+    let enabled = entry.enabled;
 
-    let result: Result<()> = (|| {
+    let result: Result<bool> = (|| {
         let args: Vec<&str> = entry.args.iter().map(|s| s.as_str()).collect();
-        let content = run_cmd(&entry.command, &args, None)
+        let content = run_cmd_with_env(&entry.command, &args, None, entry.env.as_ref())
             .with_context(|| format!("Command {} failed for {}", entry.command, id))?;
 
         let content = strip_ansi_codes(&content);
+        let parsed = match entry.format {
+            PackageOutputFormat::Text => None,
+            PackageOutputFormat::Json => Some(parse_package_list(&entry.command, &content)),
+        };
+        let written_as_text = matches!(parsed, Some(None));
+        let content = match parsed {
+            Some(Some(packages)) => {
+                let mut json = serde_json::to_string_pretty(&packages)?;
+                json.push('\n');
+                json
+            }
+            _ if entry.normalizes() => normalize_lines(&content),
+            _ => content,
+        };
         let output_path = packages_path.join(&entry.output_file);
         let tmp_path = output_path.with_extension("tmp");
 
@@ -92,12 +182,14 @@ fn run_single_package_backup(
 
         fs::rename(&tmp_path, &output_path)
             .with_context(|| format!("Move {} into place", entry.output_file))?;
-        Ok(())
+        Ok(written_as_text)
     })();
 
     PackageBackupOutcome {
         id,
         output_file,
+        command: entry.command,
+        enabled,
         result,
     }
 }
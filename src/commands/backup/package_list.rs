@@ -0,0 +1,154 @@
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub(super) struct InstalledPackage {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+/// Parse a package manager's output into a sorted, deduplicated list.
+/// Returns `None` for commands mntn does not know how to read, and for output
+/// that has lines but no recognisable packages, so the caller can keep the
+/// raw text instead.
+pub(super) fn parse_package_list(command: &str, output: &str) -> Option<Vec<InstalledPackage>> {
+    let program = Path::new(command).file_stem()?.to_str()?;
+    let mut packages: Vec<InstalledPackage> = match program {
+        "brew" => output.lines().filter_map(parse_name_line).collect(),
+        "npm" | "bun" => output.lines().filter_map(parse_tree_line).collect(),
+        "pnpm" => parse_pnpm(output),
+        "cargo" | "uv" => output.lines().filter_map(parse_versioned_line).collect(),
+        "pip" | "pip3" => output.lines().filter_map(parse_freeze_line).collect(),
+        _ => return None,
+    };
+
+    if packages.is_empty() && output.lines().any(|line| !line.trim().is_empty()) {
+        return None;
+    }
+    packages.sort();
+    packages.dedup();
+    Some(packages)
+}
+
+/// Tree markers `npm ls` and `bun pm ls` put before top-level packages. The
+/// last one differs from the rest, so they are dropped before sorting.
+const TREE_MARKERS: [&str; 4] = ["├── ", "└── ", "+-- ", "`-- "];
+
+/// Sort raw package manager output and drop blank and duplicate lines.
+/// Indented lines stay under the line they belong to, such as the binaries
+/// `cargo install --list` prints below each crate, and top-level tree
+/// markers are removed so reordering does not change them.
+pub(super) fn normalize_lines(output: &str) -> String {
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    for line in output.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let continues_block =
+            line.starts_with(char::is_whitespace) || line.starts_with('│') || line.starts_with('|');
+        match blocks.last_mut() {
+            Some(block) if continues_block => block.push(line),
+            _ => {
+                let head = TREE_MARKERS
+                    .iter()
+                    .find_map(|marker| line.strip_prefix(marker))
+                    .unwrap_or(line);
+                blocks.push(vec![head]);
+            }
+        }
+    }
+    blocks.sort();
+    blocks.dedup();
+
+    let mut normalized = String::new();
+    for line in blocks.iter().flatten() {
+        normalized.push_str(line);
+        normalized.push('\n');
+    }
+    normalized
+}
+
+/// `brew leaves`, `brew list --cask`: one name per line.
+fn parse_name_line(line: &str) -> Option<InstalledPackage> {
+    let name = line.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(InstalledPackage {
+        name: name.to_string(),
+        version: None,
+    })
+}
+
+/// `npm ls -g`, `bun pm ls -g`: `├── name@version` (`+-- name@version`
+/// outside a UTF-8 locale), with scoped names such as `@scope/name@version`.
+/// The header line (the install prefix) is skipped.
+fn parse_tree_line(line: &str) -> Option<InstalledPackage> {
+    let (_, rest) = line.split_once("── ").or_else(|| line.split_once("-- "))?;
+    let spec = rest.split_whitespace().next()?;
+    match spec.rsplit_once('@') {
+        Some((name, version)) if !name.is_empty() => Some(InstalledPackage {
+            name: name.to_string(),
+            version: Some(version.to_string()),
+        }),
+        _ => Some(InstalledPackage {
+            name: spec.to_string(),
+            version: None,
+        }),
+    }
+}
+
+/// `pnpm ls -g`: `name version` lines under a `dependencies:` heading.
+fn parse_pnpm(output: &str) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+    let mut in_dependencies = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if line.ends_with("dependencies:") {
+            in_dependencies = true;
+            continue;
+        }
+        if line.is_empty() {
+            in_dependencies = false;
+            continue;
+        }
+        if !in_dependencies {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        if let Some(name) = parts.next() {
+            packages.push(InstalledPackage {
+                name: name.to_string(),
+                version: parts.next().map(str::to_string),
+            });
+        }
+    }
+    packages
+}
+
+/// `cargo install --list` (`ripgrep v14.1.0:`) and `uv tool list`
+/// (`ruff v0.4.1`). The indented lines listing binaries are skipped.
+fn parse_versioned_line(line: &str) -> Option<InstalledPackage> {
+    if line.starts_with(char::is_whitespace) || line.starts_with('-') {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let name = parts.next()?;
+    let version = parts.next()?.trim_end_matches(':');
+    let version = version.strip_prefix('v')?;
+    Some(InstalledPackage {
+        name: name.to_string(),
+        version: Some(version.to_string()),
+    })
+}
+
+/// `pip list --format=freeze`: `name==version`.
+fn parse_freeze_line(line: &str) -> Option<InstalledPackage> {
+    let (name, version) = line.trim().split_once("==")?;
+    Some(InstalledPackage {
+        name: name.to_string(),
+        version: Some(version.to_string()),
+    })
+}
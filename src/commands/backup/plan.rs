@@ -0,0 +1,132 @@
+use crate::registry::EntryFilter;
+use crate::registry::config::{ConfigRegistry, ConfigRegistryEntry};
+use crate::registry::encrypted::EncryptedRegistry;
+use crate::registry::package::PackageRegistry;
+use crate::utils::display::{entry_status, status, warning, yellow};
+use crate::utils::filesystem::calculate_dir_size_parallel;
+use crate::utils::format::bytes_to_human_readable;
+use crate::utils::glob::GlobPattern;
+use crate::utils::paths::{
+    get_config_registry_path, get_encrypted_registry_path, get_package_registry_path,
+};
+use crate::utils::system::is_command_available;
+use anyhow::{Context, Result};
+use std::fs;
+
+/// Print what `mntn backup` would copy and how large each live target is,
+/// without writing anything. `config_filter` selects the config entries and
+/// `filter` the package and encrypted ones.
+pub fn print_backup_plan(
+    config_filter: &EntryFilter,
+    filter: &EntryFilter,
+    follow_symlinks: bool,
+    skip_encrypted: bool,
+    ignore_missing_commands: bool,
+) -> Result<()> {
+    status!("   Dry run: nothing will be written");
+
+    let config_registry_path = get_config_registry_path();
+    let config_registry = ConfigRegistry::load_or_create(&config_registry_path)
+        .with_context(|| format!("Load config registry: {}", config_registry_path.display()))?;
+    let entries: Vec<_> = config_registry.filtered_entries(config_filter).collect();
+    status!("   Configurations: {} entries", entries.len());
+
+    let mut total: u64 = 0;
+    let mut missing = 0;
+    for (id, entry) in &entries {
+        match config_entry_size(entry, follow_symlinks) {
+            Some(size) => {
+                total += size;
+                entry_status!(
+                    "     would back up {} ({})",
+                    entry.source_path,
+                    bytes_to_human_readable(size, true)
+                );
+            }
+            None => {
+                missing += 1;
+                warning!(
+                    "{}",
+                    yellow(&format!(
+                        "     missing {} ({}): nothing at {}",
+                        entry.source_path,
+                        id,
+                        entry.target_path.display()
+                    ))
+                );
+            }
+        }
+    }
+
+    let package_registry_path = get_package_registry_path();
+    let package_registry = PackageRegistry::load_or_create(&package_registry_path)
+        .with_context(|| format!("Load package registry: {}", package_registry_path.display()))?;
+    let package_count = package_registry
+        .get_platform_compatible_entries(&PackageRegistry::get_current_platform(), filter)
+        .filter(|(_, entry)| !ignore_missing_commands || is_command_available(&entry.command))
+        .count();
+    status!("   Package managers: {} would be queried", package_count);
+
+    if !skip_encrypted {
+        let registry_path = get_encrypted_registry_path();
+        let encrypted_registry = EncryptedRegistry::load_or_create(&registry_path)
+            .with_context(|| format!("Load encrypted registry: {}", registry_path.display()))?;
+        let mut count = 0;
+        let mut size: u64 = 0;
+        for (_, entry) in encrypted_registry.filtered_entries(filter) {
+            if let Ok(metadata) = fs::metadata(&entry.target_path)
+                && metadata.is_file()
+            {
+                count += 1;
+                size += metadata.len();
+            }
+        }
+        total += size;
+        status!(
+            "   Encrypted configs: {} files ({} before encryption)",
+            count,
+            bytes_to_human_readable(size, true)
+        );
+    }
+
+    status!(
+        "Dry run complete. About {} would be backed up, {} config entries missing",
+        bytes_to_human_readable(total, true),
+        missing
+    );
+    Ok(())
+}
+
+/// Bytes a config entry would copy, or `None` when its target does not exist
+/// (or, for a glob entry, matches nothing).
+fn config_entry_size(entry: &ConfigRegistryEntry, follow_symlinks: bool) -> Option<u64> {
+    let target_path = &entry.target_path;
+
+    if entry.glob {
+        let glob = GlobPattern::new(target_path);
+        let files = glob.files_under(glob.base()).ok()?;
+        if files.is_empty() {
+            return None;
+        }
+        return Some(
+            files
+                .iter()
+                .filter_map(|relative| fs::metadata(glob.base().join(relative)).ok())
+                .map(|metadata| metadata.len())
+                .sum(),
+        );
+    }
+
+    if entry.preserve_symlink && target_path.is_symlink() {
+        return fs::symlink_metadata(target_path)
+            .ok()
+            .map(|metadata| metadata.len());
+    }
+
+    let metadata = fs::metadata(target_path).ok()?;
+    if metadata.is_dir() {
+        Some(calculate_dir_size_parallel(target_path, follow_symlinks))
+    } else {
+        Some(metadata.len())
+    }
+}
@@ -0,0 +1,90 @@
+use crate::commands::git::ensure_gitignore_entry;
+use crate::utils::display::{green, status, warning, yellow};
+use crate::utils::filesystem::{calculate_dir_size_parallel, copy_dir_keeping_symlinks};
+use crate::utils::format::bytes_to_human_readable;
+use crate::utils::paths::{SNAPSHOTS_DIR, get_backup_path, get_mntn_dir, get_snapshots_path};
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+
+/// Copy the whole backup tree into `~/.mntn/snapshots/<timestamp>/` and prune
+/// the oldest snapshots so at most `keep` remain.
+pub fn create_snapshot(keep: usize) -> Result<()> {
+    let backup_path = get_backup_path();
+    let snapshots_path = get_snapshots_path();
+    fs::create_dir_all(&snapshots_path)
+        .with_context(|| format!("Create snapshots directory {}", snapshots_path.display()))?;
+    ensure_gitignore_entry(&get_mntn_dir(), &format!("{}/", SNAPSHOTS_DIR))?;
+
+    let stamp = Utc::now().format("%Y-%m-%dT%H-%M-%SZ").to_string();
+    let snapshot_path = snapshots_path.join(&stamp);
+    if snapshot_path.exists() {
+        bail!("Snapshot {} already exists", stamp);
+    }
+
+    fs::create_dir(&snapshot_path)
+        .with_context(|| format!("Create snapshot directory {}", snapshot_path.display()))?;
+    // Symlinks are copied as links so preserve_symlink backups survive.
+    copy_dir_keeping_symlinks(&backup_path, &snapshot_path).with_context(|| {
+        format!(
+            "Copy {} -> {}",
+            backup_path.display(),
+            snapshot_path.display()
+        )
+    })?;
+
+    let size = calculate_dir_size_parallel(&snapshot_path, false);
+    status!(
+        "   Snapshot {} created ({})",
+        stamp,
+        bytes_to_human_readable(size, true)
+    );
+
+    prune_snapshots(keep)
+}
+
+fn prune_snapshots(keep: usize) -> Result<()> {
+    let mut snapshots = list_snapshots()?;
+    if snapshots.len() <= keep {
+        return Ok(());
+    }
+
+    snapshots.sort();
+    let excess = snapshots.len() - keep;
+    for path in snapshots.into_iter().take(excess) {
+        let size = calculate_dir_size_parallel(&path, false);
+        match fs::remove_dir_all(&path) {
+            Ok(()) => status!(
+                "     {} pruned snapshot {} ({})",
+                green("✔"),
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                bytes_to_human_readable(size, true)
+            ),
+            Err(e) => warning!(
+                "{}",
+                yellow(&format!(
+                    "     could not prune snapshot {}: {}",
+                    path.display(),
+                    e
+                ))
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+fn list_snapshots() -> Result<Vec<PathBuf>> {
+    let snapshots_path = get_snapshots_path();
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(&snapshots_path)
+        .with_context(|| format!("Read snapshots directory {}", snapshots_path.display()))?
+    {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            snapshots.push(entry.path());
+        }
+    }
+    Ok(snapshots)
+}
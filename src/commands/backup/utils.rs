@@ -1,6 +1,10 @@
+use crate::utils::display::status;
+use crate::utils::glob::GlobPattern;
 use crate::utils::system::sync_directory_contents;
+use std::collections::HashSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 pub fn backup_file(source: &Path, destination: &Path) -> std::io::Result<()> {
     if !source.exists() {
@@ -22,7 +26,7 @@ pub fn backup_file(source: &Path, destination: &Path) -> std::io::Result<()> {
             let content = fs::read(&canonical_target)?;
             fs::remove_file(source)?;
             fs::write(source, &content)?;
-            println!("Converted symlink to real file: {}", source.display());
+            status!("Converted symlink to real file: {}", source.display());
             return Ok(());
         }
     }
@@ -35,12 +39,32 @@ pub fn backup_file(source: &Path, destination: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-pub fn backup_directory(source: &Path, destination: &Path) -> std::io::Result<()> {
+/// Store `source`, a symlink, as a symlink with the same target, so the link
+/// itself is what gets versioned and restored.
+pub fn backup_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let link_target = fs::read_link(source)?;
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // An earlier backup of this entry may be a copied directory.
+    if destination.is_dir() && !destination.is_symlink() {
+        fs::remove_dir_all(destination)?;
+    }
+    crate::utils::filesystem::replace_with_symlink(&link_target, destination)
+}
+
+pub fn backup_directory(
+    source: &Path,
+    destination: &Path,
+    follow_symlinks: bool,
+    verbose: bool,
+) -> crate::errors::Result<()> {
     if !source.exists() {
         return Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
             format!("Source directory {} not found", source.display()),
-        ));
+        )
+        .into());
     }
 
     if source.is_symlink()
@@ -61,12 +85,258 @@ pub fn backup_directory(source: &Path, destination: &Path) -> std::io::Result<()
                 fs::remove_file(source)?;
             }
             fs::create_dir_all(source)?;
-            crate::utils::filesystem::copy_dir_recursive(&canonical_target, source)?;
-            println!("Converted symlink to real directory: {}", source.display());
+            crate::utils::filesystem::copy_dir_recursive(&canonical_target, source, false)?;
+            status!("Converted symlink to real directory: {}", source.display());
             return Ok(());
         }
     }
 
+    if follow_symlinks {
+        // rsync -L has no cycle detection, so linked content goes through the
+        // guarded copy instead. Starting from an empty directory keeps the
+        // same result as rsync --delete.
+        replace_dir_with_copy(source, destination)?;
+        return Ok(());
+    }
+
     fs::create_dir_all(destination)?;
-    sync_directory_contents(source, destination)
+    sync_directory_contents(source, destination, verbose)
+}
+
+/// Copy `source` (following symlinks) into a sibling of `destination`, then
+/// swap it into place, so a failed copy leaves the previous backup intact.
+fn replace_dir_with_copy(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let name = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let staging = destination.with_file_name(format!(".{}.mntn-new", name));
+    let previous = destination.with_file_name(format!(".{}.mntn-old", name));
+    for leftover in [&staging, &previous] {
+        if leftover.exists() {
+            fs::remove_dir_all(leftover)?;
+        }
+    }
+
+    fs::create_dir_all(&staging)?;
+    if let Err(e) = crate::utils::filesystem::copy_dir_recursive(source, &staging, true) {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+
+    let had_previous = destination.exists();
+    if had_previous {
+        fs::rename(destination, &previous)?;
+    }
+    if let Err(e) = fs::rename(&staging, destination) {
+        if had_previous {
+            let _ = fs::rename(&previous, destination);
+        }
+        let _ = fs::remove_dir_all(&staging);
+        return Err(e);
+    }
+    if had_previous {
+        fs::remove_dir_all(&previous)?;
+    }
+    Ok(())
+}
+
+/// Whether `path` was modified at or after `cutoff`. Files whose mtime cannot
+/// be read count as modified, so they are backed up rather than missed.
+pub fn modified_since(path: &Path, cutoff: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_or(true, |modified| modified >= cutoff)
+}
+
+/// Copy the files under `source` modified at or after `cutoff` into
+/// `destination`, leaving older files already in the backup untouched.
+/// Returns how many files were copied.
+pub fn backup_recent_files(
+    source: &Path,
+    destination: &Path,
+    cutoff: SystemTime,
+    follow_symlinks: bool,
+) -> std::io::Result<usize> {
+    if !source.is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("Source directory {} not found", source.display()),
+        ));
+    }
+
+    let mut visited = HashSet::new();
+    crate::utils::filesystem::mark_dir_visited(&mut visited, source);
+    let mut pending: Vec<(PathBuf, PathBuf)> =
+        vec![(source.to_path_buf(), destination.to_path_buf())];
+    let mut copied = 0;
+
+    while let Some((src_dir, dst_dir)) = pending.pop() {
+        for entry in fs::read_dir(&src_dir)? {
+            let entry = entry?;
+            let src_path = entry.path();
+            let dst_path = dst_dir.join(entry.file_name());
+
+            let metadata = if follow_symlinks {
+                fs::metadata(&src_path)
+            } else {
+                fs::symlink_metadata(&src_path)
+            };
+            let Ok(metadata) = metadata else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                if crate::utils::filesystem::mark_dir_visited(&mut visited, &src_path) {
+                    pending.push((src_path, dst_path));
+                }
+            } else if metadata.is_file() && modified_since(&src_path, cutoff) {
+                fs::create_dir_all(&dst_dir)?;
+                fs::copy(&src_path, &dst_path)?;
+                copied += 1;
+            }
+        }
+    }
+
+    Ok(copied)
+}
+
+/// Suffix for an entry's progress line when it was only backed up because of
+/// `--include-disabled`.
+pub fn disabled_marker(enabled: bool) -> &'static str {
+    if enabled { "" } else { " (disabled)" }
+}
+
+/// Suffix for a section's entry count noting how many disabled entries were included.
+pub fn disabled_summary(disabled: usize) -> String {
+    if disabled == 0 {
+        String::new()
+    } else {
+        format!(" ({} disabled included)", disabled)
+    }
+}
+
+/// Copy the files matching a glob entry's `pattern` into `destination`,
+/// keeping their paths relative to the pattern's fixed base. With `since`,
+/// only files modified after it are copied; otherwise backed-up files that no
+/// longer match are removed, as a directory copy would. Returns how many files
+/// were copied and the relative paths of every matching file.
+pub fn backup_glob(
+    pattern: &Path,
+    destination: &Path,
+    since: Option<SystemTime>,
+) -> anyhow::Result<(u32, Vec<PathBuf>)> {
+    let glob = GlobPattern::new(pattern);
+    let matched = glob.files_under(glob.base())?;
+    if matched.is_empty() {
+        anyhow::bail!("no files match {}", pattern.display());
+    }
+
+    let mut copied = 0;
+    for relative in &matched {
+        let source = glob.base().join(relative);
+        let target = destination.join(relative);
+        if since.is_some_and(|cutoff| target.exists() && !modified_since(&source, cutoff)) {
+            continue;
+        }
+        backup_file(&source, &target)?;
+        copied += 1;
+    }
+
+    if since.is_none() && destination.is_dir() {
+        for relative in glob.files_under(destination)? {
+            if !matched.contains(&relative) {
+                let stale = destination.join(&relative);
+                fs::remove_file(&stale)?;
+                remove_empty_parents(&stale, destination)?;
+            }
+        }
+    }
+
+    Ok((copied, matched))
+}
+
+/// Remove the directories above `removed` that are now empty, stopping at
+/// `root`, which is kept.
+fn remove_empty_parents(removed: &Path, root: &Path) -> std::io::Result<()> {
+    for dir in removed.ancestors().skip(1) {
+        if dir == root || !dir.starts_with(root) {
+            break;
+        }
+        if fs::read_dir(dir)?.next().is_some() {
+            break;
+        }
+        fs::remove_dir(dir)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pruning_a_glob_removes_emptied_directories() {
+        let dir = std::env::temp_dir().join(format!("mntn-glob-prune-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let live = dir.join("live");
+        let backup = dir.join("backup");
+        for file in ["a/one.conf", "a/deep/two.conf", "b/three.conf"] {
+            fs::create_dir_all(live.join(file).parent().unwrap()).unwrap();
+            fs::write(live.join(file), file).unwrap();
+        }
+        let pattern = live.join("**/*.conf");
+        backup_glob(&pattern, &backup, None).unwrap();
+        assert!(backup.join("a/deep/two.conf").is_file());
+
+        fs::remove_dir_all(live.join("a/deep")).unwrap();
+        fs::remove_dir_all(live.join("b")).unwrap();
+        backup_glob(&pattern, &backup, None).unwrap();
+
+        assert!(backup.join("a/one.conf").is_file());
+        assert!(!backup.join("a/deep").exists());
+        assert!(!backup.join("b").exists());
+        assert!(backup.is_dir());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn followed_copy_replaces_the_previous_backup_in_one_step() {
+        let dir = std::env::temp_dir().join(format!("mntn-dir-swap-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (live, backup) = (dir.join("live"), dir.join("backup/nvim"));
+        fs::create_dir_all(live.join("lua")).unwrap();
+        fs::write(live.join("lua/init.lua"), "new").unwrap();
+        fs::create_dir_all(&backup).unwrap();
+        fs::write(backup.join("stale.lua"), "old").unwrap();
+
+        replace_dir_with_copy(&live, &backup).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(backup.join("lua/init.lua")).unwrap(),
+            "new"
+        );
+        assert!(!backup.join("stale.lua").exists());
+        let names: Vec<_> = fs::read_dir(dir.join("backup"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["nvim"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_copy_keeps_the_previous_backup() {
+        let dir = std::env::temp_dir().join(format!("mntn-dir-keep-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let backup = dir.join("backup/nvim");
+        fs::create_dir_all(&backup).unwrap();
+        fs::write(backup.join("init.lua"), "old").unwrap();
+
+        assert!(replace_dir_with_copy(&dir.join("missing"), &backup).is_err());
+
+        assert_eq!(fs::read_to_string(backup.join("init.lua")).unwrap(), "old");
+        assert_eq!(fs::read_dir(dir.join("backup")).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
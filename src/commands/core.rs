@@ -1,4 +1,4 @@
-use crate::utils::display::{green, red};
+use crate::utils::display::{green, red, status};
 use anyhow::Result;
 
 pub(crate) trait Command {
@@ -6,16 +6,78 @@ pub(crate) trait Command {
     fn execute(&mut self) -> Result<()>;
 }
 
+/// Failure that should end with a specific exit status instead of the default
+/// 1, for commands whose outcomes scripts need to tell apart.
+#[derive(Debug)]
+pub(crate) struct ExitCodeError {
+    pub(crate) code: i32,
+    pub(crate) message: String,
+}
+
+impl ExitCodeError {
+    pub(crate) fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ExitCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ExitCodeError {}
+
 pub(crate) struct CommandExecutor;
 
 impl CommandExecutor {
-    pub(crate) fn run<T: Command>(task: &mut T) {
+    /// Run `task` and report how it went. Returns the exit status: 0 on
+    /// success, the code of an [`ExitCodeError`], or 1 for any other error.
+    /// Exiting is left to the caller.
+    pub(crate) fn run<T: Command + ?Sized>(task: &mut T) -> i32 {
         let name = task.name().to_string();
 
         if let Err(e) = task.execute() {
-            eprintln!("{}", red(&format!("Error during {}: {}", name, e)));
-            return;
+            eprintln!("{}", red(&format!("Error during {}: {:#}", name, e)));
+            return e.downcast_ref::<ExitCodeError>().map_or(1, |e| e.code);
         }
-        println!("{}", green(&format!("{} complete", name)));
+        status!("{}", green(&format!("{} complete", name)));
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Task(Option<anyhow::Error>);
+
+    impl Command for Task {
+        fn name(&self) -> &str {
+            "Test"
+        }
+
+        fn execute(&mut self) -> Result<()> {
+            match self.0.take() {
+                Some(error) => Err(error),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn exit_status_is_returned_to_the_caller() {
+        assert_eq!(CommandExecutor::run(&mut Task(None)), 0);
+        assert_eq!(
+            CommandExecutor::run(&mut Task(Some(anyhow::anyhow!("boom")))),
+            1
+        );
+        assert_eq!(
+            CommandExecutor::run(&mut Task(Some(ExitCodeError::new(3, "rejected").into()))),
+            3
+        );
     }
 }
@@ -0,0 +1,315 @@
+use crate::cli::DiffArgs;
+use crate::commands::core::Command;
+use crate::profiles::ActiveProfile;
+use crate::registry::config::ConfigRegistry;
+use crate::utils::display::{green, red, yellow};
+use crate::utils::glob::GlobPattern;
+use crate::utils::paths::get_config_registry_path;
+use anyhow::Result;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Lines of unchanged context printed around each change.
+const CONTEXT_LINES: usize = 3;
+/// Above this many line pairs the LCS table gets too large; report a plain difference instead.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+struct DiffTask {
+    profile: ActiveProfile,
+}
+
+impl DiffTask {
+    fn new(profile: ActiveProfile) -> Self {
+        Self { profile }
+    }
+}
+
+impl Command for DiffTask {
+    fn name(&self) -> &str {
+        "Diff"
+    }
+
+    fn execute(&mut self) -> Result<()> {
+        let config_registry = ConfigRegistry::load_or_create(&get_config_registry_path())?;
+        let enabled_entries: Vec<_> = config_registry.get_enabled_entries().collect();
+
+        println!("Comparing backup with live files...");
+        println!("   Profile: {}", self.profile);
+        println!();
+
+        let mut differing = 0;
+        for (id, entry) in enabled_entries {
+            let Some(resolved) = self.profile.resolve_source(&entry.source_path) else {
+                println!(
+                    "{}",
+                    yellow(&format!("{} ({}): no backup in any layer", entry.name, id))
+                );
+                continue;
+            };
+
+            let target_path = &entry.target_path;
+            if entry.glob {
+                let header = format!("{} ({}) [{}]", entry.name, id, resolved.layer);
+                if diff_glob(&header, &resolved.path, target_path)? {
+                    differing += 1;
+                }
+                continue;
+            }
+            if !target_path.exists() {
+                differing += 1;
+                println!(
+                    "{}",
+                    yellow(&format!(
+                        "{} ({}): live file {} is missing",
+                        entry.name,
+                        id,
+                        target_path.display()
+                    ))
+                );
+                continue;
+            }
+
+            let header = format!("{} ({}) [{}]", entry.name, id, resolved.layer);
+            let changed = if resolved.path.is_dir() || target_path.is_dir() {
+                diff_directories(&header, &resolved.path, target_path)?
+            } else {
+                diff_files(&header, &resolved.path, target_path)?
+            };
+            if changed {
+                differing += 1;
+            }
+        }
+
+        println!();
+        if differing == 0 {
+            println!("{}", green("Backup and live files are identical"));
+        } else {
+            println!("{} entries differ", differing);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn task(args: DiffArgs) -> Box<dyn Command> {
+    let profile = args.resolve_profile();
+    Box::new(DiffTask::new(profile))
+}
+
+fn diff_files(header: &str, backup: &Path, live: &Path) -> Result<bool> {
+    let backup_content = fs::read(backup)?;
+    let live_content = fs::read(live)?;
+    if backup_content == live_content {
+        return Ok(false);
+    }
+
+    println!("{}", header);
+    match (as_text(&backup_content), as_text(&live_content)) {
+        (Some(old), Some(new)) => {
+            println!("{}", red(&format!("--- {} (backup)", backup.display())));
+            println!("{}", green(&format!("+++ {} (live)", live.display())));
+            print_unified_diff(old, new);
+        }
+        _ => println!("   binary files differ"),
+    }
+    println!();
+    Ok(true)
+}
+
+fn diff_directories(header: &str, backup: &Path, live: &Path) -> Result<bool> {
+    if !backup.is_dir() || !live.is_dir() {
+        println!("{}", header);
+        println!("   one side is a directory and the other is a file");
+        println!();
+        return Ok(true);
+    }
+
+    diff_file_sets(header, backup, &list_files(backup), live, &list_files(live))
+}
+
+/// Compare the files of a glob entry, matched by its pattern on both sides.
+fn diff_glob(header: &str, backup: &Path, pattern: &Path) -> Result<bool> {
+    let glob = GlobPattern::new(pattern);
+    let backup_files = glob.files_under(backup)?.into_iter().collect();
+    let live_files = glob.files_under(glob.base())?.into_iter().collect();
+    diff_file_sets(header, backup, &backup_files, glob.base(), &live_files)
+}
+
+/// Report files only on one side, and files whose contents differ, given the
+/// relative paths found under each root.
+fn diff_file_sets(
+    header: &str,
+    backup: &Path,
+    backup_files: &BTreeSet<PathBuf>,
+    live: &Path,
+    live_files: &BTreeSet<PathBuf>,
+) -> Result<bool> {
+    let removed: Vec<_> = backup_files.difference(live_files).collect();
+    let added: Vec<_> = live_files.difference(backup_files).collect();
+    let mut changed = Vec::new();
+    for relative in backup_files.intersection(live_files) {
+        if fs::read(backup.join(relative))? != fs::read(live.join(relative))? {
+            changed.push(relative);
+        }
+    }
+
+    if removed.is_empty() && added.is_empty() && changed.is_empty() {
+        return Ok(false);
+    }
+
+    println!("{}", header);
+    for path in added {
+        println!(
+            "{}",
+            green(&format!("   + {} (only in live)", path.display()))
+        );
+    }
+    for path in removed {
+        println!(
+            "{}",
+            red(&format!("   - {} (only in backup)", path.display()))
+        );
+    }
+    for path in changed {
+        println!("{}", yellow(&format!("   ~ {}", path.display())));
+    }
+    println!();
+    Ok(true)
+}
+
+fn list_files(root: &Path) -> BTreeSet<PathBuf> {
+    fn walk(root: &Path, dir: &Path, files: &mut BTreeSet<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if metadata.is_dir() {
+                walk(root, &path, files);
+            } else if metadata.is_file()
+                && let Ok(relative) = path.strip_prefix(root)
+            {
+                files.insert(relative.to_path_buf());
+            }
+        }
+    }
+
+    let mut files = BTreeSet::new();
+    walk(root, root, &mut files);
+    files
+}
+
+fn as_text(content: &[u8]) -> Option<&str> {
+    if content.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(content).ok()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineOp {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+fn line_ops(old: &[&str], new: &[&str]) -> Vec<LineOp> {
+    let (n, m) = (old.len(), new.len());
+    let width = m + 1;
+    // lcs[i * width + j] is the LCS length of old[i..] and new[j..].
+    let mut lcs = vec![0usize; (n + 1) * width];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i * width + j] = if old[i] == new[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(LineOp::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            ops.push(LineOp::Delete(i));
+            i += 1;
+        } else {
+            ops.push(LineOp::Insert(j));
+            j += 1;
+        }
+    }
+    ops.extend((i..n).map(LineOp::Delete));
+    ops.extend((j..m).map(LineOp::Insert));
+    ops
+}
+
+fn print_unified_diff(old: &str, new: &str) {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    if old_lines.len().saturating_mul(new_lines.len()) > MAX_DIFF_CELLS {
+        println!("   files differ (too large for a line diff)");
+        return;
+    }
+
+    let ops = line_ops(&old_lines, &new_lines);
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, LineOp::Equal(..)))
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changes {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let hunk = &ops[start..end];
+        let old_start = hunk.iter().find_map(|op| match op {
+            LineOp::Equal(i, _) | LineOp::Delete(i) => Some(*i + 1),
+            LineOp::Insert(_) => None,
+        });
+        let new_start = hunk.iter().find_map(|op| match op {
+            LineOp::Equal(_, j) | LineOp::Insert(j) => Some(*j + 1),
+            LineOp::Delete(_) => None,
+        });
+        let old_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, LineOp::Insert(_)))
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|op| !matches!(op, LineOp::Delete(_)))
+            .count();
+
+        println!(
+            "@@ -{},{} +{},{} @@",
+            old_start.unwrap_or(0),
+            old_len,
+            new_start.unwrap_or(0),
+            new_len
+        );
+        for op in hunk {
+            match *op {
+                LineOp::Equal(i, _) => println!(" {}", old_lines[i]),
+                LineOp::Delete(i) => println!("{}", red(&format!("-{}", old_lines[i]))),
+                LineOp::Insert(j) => println!("{}", green(&format!("+{}", new_lines[j]))),
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use crate::commands::core::Command;
 use crate::commands::doctor::utils::enabled_json_files;
 use crate::profiles::ActiveProfile;
+use crate::registry::config::ConfigRegistry;
 use crate::utils::display::{green, red, yellow};
+use crate::utils::paths::get_config_registry_path;
 use std::fs;
 use std::path::Path;
 
@@ -39,7 +41,8 @@ impl Command for FixTask {
         }
         println!("   Profile: {}", self.profile);
 
-        let files = enabled_json_files(&self.profile)?;
+        let config_registry = ConfigRegistry::load_or_create(&get_config_registry_path())?;
+        let files = enabled_json_files(&self.profile, &config_registry);
         if files.is_empty() {
             println!("{}", green("No JSON config files to format"));
             return Ok(());
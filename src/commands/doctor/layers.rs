@@ -0,0 +1,88 @@
+use crate::profiles::{ActiveProfile, SourceLayer};
+use crate::registry::config::ConfigRegistry;
+use crate::utils::display::{green, yellow};
+use crate::utils::paths::get_config_registry_path;
+use anyhow::{Context, Result};
+
+/// Print a table of every enabled config entry: the layer restore would use
+/// and which layers hold a copy. The profile column is left out when no
+/// profile is active.
+pub(super) fn print_layer_report(profile: &ActiveProfile) -> Result<()> {
+    let registry_path = get_config_registry_path();
+    let registry = ConfigRegistry::load_or_create(&registry_path)
+        .with_context(|| format!("Load config registry: {}", registry_path.display()))?;
+    let mut entries: Vec<_> = registry.get_enabled_entries().collect();
+    entries.sort_by_key(|(id, _)| *id);
+
+    let id_width = entries
+        .iter()
+        .map(|(id, _)| id.len())
+        .chain(std::iter::once("ENTRY".len()))
+        .max()
+        .unwrap_or_default();
+    let has_profile = profile.name.is_some();
+
+    println!(
+        "   {:<id_width$}  {:<9}  {}COMMON",
+        "ENTRY",
+        "RESOLVED",
+        if has_profile { "PROFILE  " } else { "" }
+    );
+
+    let mut missing = 0;
+    for (id, entry) in entries {
+        let layers: Vec<SourceLayer> = profile
+            .get_all_resolved_sources(&entry.source_path)
+            .into_iter()
+            .map(|source| source.layer)
+            .collect();
+        let resolved = if entry.profile_required {
+            layers.iter().find(|layer| **layer == SourceLayer::Profile)
+        } else {
+            layers.first()
+        };
+        let resolved = match resolved {
+            Some(layer) => format!("{:<9}", layer.to_string()),
+            None => {
+                missing += 1;
+                yellow(&format!("{:<9}", "missing"))
+            }
+        };
+        let mark = |layer: SourceLayer| {
+            if layers.contains(&layer) {
+                green("✔")
+            } else {
+                "·".to_string()
+            }
+        };
+
+        println!(
+            "   {:<id_width$}  {}  {}{}{}",
+            id,
+            resolved,
+            if has_profile {
+                format!("{}        ", mark(SourceLayer::Profile))
+            } else {
+                String::new()
+            },
+            mark(SourceLayer::Common),
+            if entry.profile_required {
+                "  (profile_required)"
+            } else {
+                ""
+            }
+        );
+    }
+
+    if missing > 0 {
+        println!();
+        println!(
+            "{}",
+            yellow(&format!(
+                "{} entries have no backup restore would use; run mntn backup",
+                missing
+            ))
+        );
+    }
+    Ok(())
+}
@@ -1,28 +1,100 @@
 use crate::cli::DoctorActions;
-use crate::commands::core::{Command, CommandExecutor};
+use crate::commands::core::Command;
 use crate::profiles::{ActiveProfile, ProfileConfig};
-use crate::utils::display::{green, red};
+use crate::utils::display::{green, red, send_status_to_stderr, status};
+use serde::Serialize;
 
 mod fix;
+mod layers;
 mod types;
 mod utils;
 mod validators;
 
-use validators::ValidationSuite;
+pub(crate) use validators::ValidationSuite;
+
+/// Bumped whenever a field of `doctor --json` is renamed, removed, or changes
+/// meaning. New fields may be added without a bump.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct DoctorJson<'a> {
+    schema_version: u32,
+    /// `None` when only the common layer is in use.
+    profile: Option<&'a str>,
+    tools: Vec<validators::ToolStatus>,
+    checks: Vec<types::CheckResult<'a>>,
+    errors: usize,
+    warnings: usize,
+}
 
 struct DoctorTask {
     profile: ActiveProfile,
     skip_encrypted: bool,
     ask_password: bool,
+    parallel: bool,
+    json: bool,
+    entry: Option<String>,
+    layers: bool,
 }
 
 impl DoctorTask {
-    fn new(profile: ActiveProfile, skip_encrypted: bool, ask_password: bool) -> Self {
+    fn from_args(args: crate::cli::DoctorArgs) -> Self {
         Self {
-            profile,
-            skip_encrypted,
-            ask_password,
+            profile: args.resolve_profile(),
+            skip_encrypted: args.skip_encrypted,
+            ask_password: args.ask_password,
+            parallel: args.parallel,
+            json: args.json,
+            entry: args.entry,
+            layers: args.layers,
+        }
+    }
+
+    /// Every check, or with `--entry` only those for that entry.
+    fn suite(&self) -> anyhow::Result<ValidationSuite> {
+        let Some(id) = &self.entry else {
+            return Ok(ValidationSuite::new(
+                self.profile.clone(),
+                self.skip_encrypted,
+                self.ask_password,
+            ));
+        };
+        ValidationSuite::for_entry(
+            self.profile.clone(),
+            id,
+            self.skip_encrypted,
+            self.ask_password,
+        )
+        .ok_or_else(|| anyhow::anyhow!("No registry entry with id '{}'", id))
+    }
+
+    /// Print the tools and validator results as one JSON object on stdout.
+    /// Like the text output, any error makes the command exit with status 1.
+    fn execute_json(&self) -> anyhow::Result<()> {
+        let report = self.suite()?.run_all(self.parallel);
+        let (error_count, warning_count) = (report.error_count(), report.warning_count());
+        let output = DoctorJson {
+            schema_version: JSON_SCHEMA_VERSION,
+            profile: self.profile.name.as_deref(),
+            tools: if self.entry.is_some() {
+                Vec::new()
+            } else {
+                validators::tool_statuses()
+            },
+            checks: report.checks(),
+            errors: error_count,
+            warnings: warning_count,
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+
+        if error_count > 0 {
+            return Err(anyhow::anyhow!(
+                "Validation failed: {} error(s), {} warning(s)",
+                error_count,
+                warning_count
+            ));
         }
+        Ok(())
     }
 }
 
@@ -32,13 +104,22 @@ impl Command for DoctorTask {
     }
 
     fn execute(&mut self) -> anyhow::Result<()> {
+        if self.json {
+            return self.execute_json();
+        }
+        if self.layers {
+            println!("   Profile: {}", self.profile);
+            return layers::print_layer_report(&self.profile);
+        }
+
         println!("Validating configuration...");
         println!("   Profile: {}", self.profile);
+        if let Some(id) = &self.entry {
+            println!("   Entry: {}", id);
+        }
         println!("Starting validation");
 
-        let validator =
-            ValidationSuite::new(self.profile.clone(), self.skip_encrypted, self.ask_password);
-        let report = validator.run_all();
+        let report = self.suite()?.run_all(self.parallel);
         println!();
         report.print();
         println!();
@@ -67,23 +148,19 @@ impl Command for DoctorTask {
     }
 }
 
-pub(crate) fn run(args: crate::cli::DoctorArgs) {
+pub(crate) fn task(args: crate::cli::DoctorArgs) -> Box<dyn Command> {
+    if args.json {
+        send_status_to_stderr();
+    }
     if let Ok(true) = ProfileConfig::save_default_if_missing() {
-        println!("Created default profile config at ~/.mntn/profiles.json");
+        status!("Created default profile config at ~/.mntn/profiles.json");
     }
 
     match args.action {
         Some(DoctorActions::Fix(fix_args)) => {
             let profile = fix_args.resolve_profile();
-            CommandExecutor::run(&mut fix::FixTask::new(profile, fix_args.dry_run));
-        }
-        None => {
-            let profile = args.resolve_profile();
-            CommandExecutor::run(&mut DoctorTask::new(
-                profile,
-                args.skip_encrypted,
-                args.ask_password,
-            ));
+            Box::new(fix::FixTask::new(profile, fix_args.dry_run))
         }
+        None => Box::new(DoctorTask::from_args(args)),
     }
 }
@@ -1,16 +1,19 @@
 use crate::utils::display::{green, red, yellow};
+use serde::Serialize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
     Error,
     Warning,
     Info,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ValidationError {
     pub severity: Severity,
     pub message: String,
+    #[serde(rename = "fix")]
     pub fix_suggestion: Option<String>,
 }
 
@@ -45,11 +48,19 @@ impl ValidationError {
     }
 }
 
-pub trait Validator {
+/// Validators only read state, so `doctor --parallel` may run them on
+/// separate threads.
+pub trait Validator: Send + Sync {
     fn name(&self) -> &str;
     fn validate(&self) -> Vec<ValidationError>;
 }
 
+#[derive(Serialize)]
+pub struct CheckResult<'a> {
+    pub name: &'a str,
+    pub issues: &'a [ValidationError],
+}
+
 #[derive(Default)]
 pub struct ValidationReport {
     results: Vec<(String, Vec<ValidationError>)>,
@@ -80,6 +91,14 @@ impl ValidationReport {
         self.count_by_severity(Severity::Warning)
     }
 
+    /// Each validator's findings, in the order they ran.
+    pub fn checks(&self) -> Vec<CheckResult<'_>> {
+        self.results
+            .iter()
+            .map(|(name, issues)| CheckResult { name, issues })
+            .collect()
+    }
+
     pub fn print(&self) {
         for (name, errors) in &self.results {
             if errors.is_empty() {
@@ -1,30 +1,86 @@
 use crate::commands::doctor::types::ValidationError;
 use crate::profiles::ActiveProfile;
 use crate::registry::config::ConfigRegistry;
-use crate::utils::paths::get_config_registry_path;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Resolve every enabled `.json` config entry to its on-disk path for the given
-/// profile. Shared by the JSON validator and `doctor fix` so both walk the same
-/// set of files.
-pub fn enabled_json_files(profile: &ActiveProfile) -> anyhow::Result<Vec<(PathBuf, String)>> {
-    let config_registry_path = get_config_registry_path();
-    let config_registry = ConfigRegistry::load_or_create(&config_registry_path)?;
+/// Config formats `doctor` knows how to parse, picked by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "json" => Some(ConfigFormat::Json),
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "JSON",
+            ConfigFormat::Toml => "TOML",
+            ConfigFormat::Yaml => "YAML",
+        }
+    }
 
+    fn parse_error(self, content: &str) -> Option<String> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str::<serde_json::Value>(content)
+                .err()
+                .map(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str::<toml::Table>(content)
+                .err()
+                .map(|e| e.to_string().trim_end().to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(content)
+                .err()
+                .map(|e| e.to_string()),
+        }
+    }
+}
+
+/// Resolve every enabled JSON, TOML, or YAML config entry to its on-disk path
+/// for the given profile.
+pub fn enabled_config_files(
+    profile: &ActiveProfile,
+    config_registry: &ConfigRegistry,
+) -> Vec<(PathBuf, String, ConfigFormat)> {
     let mut files = Vec::new();
     for (_id, entry) in config_registry.get_enabled_entries() {
-        if entry.source_path.ends_with(".json")
+        if let Some(format) = ConfigFormat::from_path(&entry.source_path)
             && let Some(resolved) = profile.resolve_source(&entry.source_path)
         {
-            files.push((resolved.path, entry.name.clone()));
+            files.push((resolved.path, entry.name.clone(), format));
         }
     }
-    Ok(files)
+    files
+}
+
+/// The JSON subset of [`enabled_config_files`], which `doctor fix` reformats.
+pub fn enabled_json_files(
+    profile: &ActiveProfile,
+    config_registry: &ConfigRegistry,
+) -> Vec<(PathBuf, String)> {
+    enabled_config_files(profile, config_registry)
+        .into_iter()
+        .filter(|(_, _, format)| *format == ConfigFormat::Json)
+        .map(|(path, name, _)| (path, name))
+        .collect()
 }
 
-pub fn validate_json_file(path: &Path, description: &str) -> Vec<ValidationError> {
+pub fn validate_config_file(
+    path: &Path,
+    description: &str,
+    format: ConfigFormat,
+) -> Vec<ValidationError> {
     let mut errors = Vec::new();
     if !path.exists() {
         return errors;
@@ -39,10 +95,15 @@ pub fn validate_json_file(path: &Path, description: &str) -> Vec<ValidationError
             return errors;
         }
     };
-    if let Err(e) = serde_json::from_str::<serde_json::Value>(&content) {
+    if let Some(e) = format.parse_error(&content) {
         errors.push(
-            ValidationError::error(format!("Invalid JSON in {}: {}", description, e))
-                .with_fix(format!("Check syntax in {}", path.display())),
+            ValidationError::error(format!(
+                "Invalid {} in {}: {}",
+                format.label(),
+                description,
+                e
+            ))
+            .with_fix(format!("Check syntax in {}", path.display())),
         );
     }
     errors
@@ -5,21 +5,28 @@ use crate::encryption::{
     resolve_encryption_password,
 };
 use crate::profiles::ActiveProfile;
-use crate::registry::config::ConfigRegistry;
-use crate::registry::encrypted::EncryptedRegistry;
-use crate::utils::paths::{get_config_registry_path, get_encrypted_registry_path};
 use std::fs;
+use std::sync::Arc;
+
+use super::registries::LoadedRegistries;
 
 pub struct BackupConsistencyValidator {
     profile: ActiveProfile,
+    registries: Arc<LoadedRegistries>,
     skip_encrypted: bool,
     ask_password: bool,
 }
 
 impl BackupConsistencyValidator {
-    pub fn new(profile: ActiveProfile, skip_encrypted: bool, ask_password: bool) -> Self {
+    pub fn new(
+        profile: ActiveProfile,
+        registries: Arc<LoadedRegistries>,
+        skip_encrypted: bool,
+        ask_password: bool,
+    ) -> Self {
         Self {
             profile,
+            registries,
             skip_encrypted,
             ask_password,
         }
@@ -30,14 +37,10 @@ impl Validator for BackupConsistencyValidator {
     fn validate(&self) -> Vec<ValidationError> {
         let mut errors = Vec::new();
 
-        let config_registry_path = get_config_registry_path();
-        let config_registry = match ConfigRegistry::load_or_create(&config_registry_path) {
+        let config_registry = match self.registries.config() {
             Ok(r) => r,
-            Err(e) => {
-                errors.push(ValidationError::error(format!(
-                    "Could not load config registry: {}",
-                    e
-                )));
+            Err(error) => {
+                errors.push(error);
                 return errors;
             }
         };
@@ -98,14 +101,10 @@ impl Validator for BackupConsistencyValidator {
             return errors;
         }
 
-        let encrypted_registry_path = get_encrypted_registry_path();
-        let encrypted_registry = match EncryptedRegistry::load_or_create(&encrypted_registry_path) {
+        let encrypted_registry = match self.registries.encrypted() {
             Ok(r) => r,
-            Err(e) => {
-                errors.push(ValidationError::error(format!(
-                    "Could not load encrypted config registry: {}",
-                    e
-                )));
+            Err(error) => {
+                errors.push(error);
                 return errors;
             }
         };
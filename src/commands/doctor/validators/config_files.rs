@@ -0,0 +1,40 @@
+use crate::commands::doctor::types::{ValidationError, Validator};
+use crate::commands::doctor::utils::{enabled_config_files, validate_config_file};
+use crate::profiles::ActiveProfile;
+use std::sync::Arc;
+
+use super::registries::LoadedRegistries;
+
+pub struct ConfigFilesValidator {
+    profile: ActiveProfile,
+    registries: Arc<LoadedRegistries>,
+}
+
+impl ConfigFilesValidator {
+    pub fn new(profile: ActiveProfile, registries: Arc<LoadedRegistries>) -> Self {
+        Self {
+            profile,
+            registries,
+        }
+    }
+}
+
+impl Validator for ConfigFilesValidator {
+    fn validate(&self) -> Vec<ValidationError> {
+        let files = match self.registries.config() {
+            Ok(registry) => enabled_config_files(&self.profile, registry),
+            Err(error) => return vec![error],
+        };
+
+        let mut errors = Vec::new();
+        for (path, name, format) in files {
+            errors.extend(validate_config_file(&path, &name, format));
+        }
+
+        errors
+    }
+
+    fn name(&self) -> &str {
+        "Configuration Files (JSON, TOML, YAML)"
+    }
+}
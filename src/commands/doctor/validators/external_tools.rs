@@ -0,0 +1,102 @@
+use super::registries::LoadedRegistries;
+use crate::commands::doctor::types::{ValidationError, Validator};
+use crate::utils::system::{is_command_available, run_cmd};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// External programs mntn shells out to, with the features that depend on them.
+const TOOLS: &[(&str, &str)] = &[
+    ("git", "backup (repository init), sync, git"),
+    ("rsync", "backup and restore of directory entries"),
+];
+
+/// Whether one external tool is available, as reported by `doctor --json`.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ToolStatus {
+    pub tool: &'static str,
+    pub present: bool,
+    /// First line of `<tool> --version`, when it could be read.
+    pub version: Option<String>,
+    pub required_by: &'static str,
+}
+
+/// Look up every tool mntn shells out to.
+pub(crate) fn tool_statuses() -> Vec<ToolStatus> {
+    TOOLS
+        .iter()
+        .map(|(tool, required_by)| {
+            let present = is_command_available(tool);
+            ToolStatus {
+                tool,
+                present,
+                version: if present { tool_version(tool) } else { None },
+                required_by,
+            }
+        })
+        .collect()
+}
+
+pub struct ExternalToolsValidator {
+    registries: Arc<LoadedRegistries>,
+}
+
+impl ExternalToolsValidator {
+    pub fn new(registries: Arc<LoadedRegistries>) -> Self {
+        Self { registries }
+    }
+
+    fn has_directory_entries(&self) -> bool {
+        self.registries
+            .config()
+            .map(|registry| {
+                registry
+                    .get_enabled_entries()
+                    .any(|(_, entry)| entry.target_path.is_dir())
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl Validator for ExternalToolsValidator {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        for status in tool_statuses() {
+            let tool = status.tool;
+            if status.present {
+                let version = status
+                    .version
+                    .unwrap_or_else(|| "version unknown".to_string());
+                errors.push(ValidationError::info(format!("{}: {}", tool, version)));
+                continue;
+            }
+
+            let message = format!(
+                "{} not found in PATH (needed for {})",
+                tool, status.required_by
+            );
+            let critical = tool == "git" || self.has_directory_entries();
+            let error = if critical {
+                ValidationError::error(message)
+            } else {
+                ValidationError::warning(message)
+            };
+            errors.push(error.with_fix(format!("Install {} and make sure it is on PATH", tool)));
+        }
+
+        errors
+    }
+
+    fn name(&self) -> &str {
+        "External Tools"
+    }
+}
+
+fn tool_version(tool: &str) -> Option<String> {
+    let output = run_cmd(tool, &["--version"], None).ok()?;
+    output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
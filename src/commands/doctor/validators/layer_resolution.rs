@@ -1,29 +1,29 @@
+use super::registries::LoadedRegistries;
 use crate::commands::doctor::types::{ValidationError, Validator};
 use crate::profiles::ActiveProfile;
-use crate::registry::config::ConfigRegistry;
-use crate::utils::paths::get_config_registry_path;
+use std::sync::Arc;
 
 pub struct LayerResolutionValidator {
     profile: ActiveProfile,
+    registries: Arc<LoadedRegistries>,
 }
 
 impl LayerResolutionValidator {
-    pub fn new(profile: ActiveProfile) -> Self {
-        Self { profile }
+    pub fn new(profile: ActiveProfile, registries: Arc<LoadedRegistries>) -> Self {
+        Self {
+            profile,
+            registries,
+        }
     }
 }
 
 impl Validator for LayerResolutionValidator {
     fn validate(&self) -> Vec<ValidationError> {
         let mut errors = Vec::new();
-        let config_registry_path = get_config_registry_path();
-        let config_registry = match ConfigRegistry::load_or_create(&config_registry_path) {
+        let config_registry = match self.registries.config() {
             Ok(r) => r,
-            Err(e) => {
-                errors.push(ValidationError::error(format!(
-                    "Could not load config registry: {}",
-                    e
-                )));
+            Err(error) => {
+                errors.push(error);
                 return errors;
             }
         };
@@ -1,7 +1,14 @@
 mod backup_consistency;
-mod json_files;
+mod config_files;
+mod external_tools;
 mod layer_resolution;
+mod permissions;
+mod registries;
 mod registry_files;
+mod single_entry;
+mod symlink_loops;
+mod target_paths;
 mod validation_suite;
 
+pub(crate) use external_tools::{ToolStatus, tool_statuses};
 pub(crate) use validation_suite::ValidationSuite;
@@ -0,0 +1,135 @@
+use super::registries::LoadedRegistries;
+use crate::commands::doctor::types::{ValidationError, Validator};
+use directories_next::BaseDirs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Paths under the home directory that hold credentials or keys. Config
+/// entries targeting these (or anything inside them) get their mode checked.
+const SENSITIVE_PATHS: &[&str] = &[
+    ".ssh",
+    ".gnupg",
+    ".netrc",
+    ".pgpass",
+    ".git-credentials",
+    ".aws/credentials",
+    ".docker/config.json",
+    ".kube/config",
+];
+
+pub struct PermissionsValidator {
+    registries: Arc<LoadedRegistries>,
+}
+
+/// A live target to check, with the entry's `mode` if it sets one.
+struct CheckedTarget {
+    id: String,
+    name: String,
+    path: PathBuf,
+    mode: Option<u32>,
+}
+
+impl PermissionsValidator {
+    pub fn new(registries: Arc<LoadedRegistries>) -> Self {
+        Self { registries }
+    }
+
+    /// Enabled entries whose live target should not be readable by others:
+    /// every encrypted entry, plus config entries that set a `mode` or sit
+    /// under a sensitive path.
+    fn sensitive_targets(&self, errors: &mut Vec<ValidationError>) -> Vec<CheckedTarget> {
+        let mut targets = Vec::new();
+        let home_dir = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+
+        match self.registries.config() {
+            Ok(registry) => {
+                for (id, entry) in registry.get_enabled_entries() {
+                    let sensitive = home_dir
+                        .as_deref()
+                        .is_some_and(|home_dir| is_sensitive(home_dir, &entry.target_path));
+                    if entry.mode.is_some() || sensitive {
+                        targets.push(CheckedTarget {
+                            id: id.clone(),
+                            name: entry.name.clone(),
+                            path: entry.target_path.clone(),
+                            mode: entry.mode,
+                        });
+                    }
+                }
+            }
+            Err(error) => errors.push(error),
+        }
+
+        match self.registries.encrypted() {
+            Ok(registry) => {
+                targets.extend(registry.get_enabled_entries().map(|(id, e)| CheckedTarget {
+                    id: id.clone(),
+                    name: e.name.clone(),
+                    path: e.target_path.clone(),
+                    mode: None,
+                }))
+            }
+            Err(error) => errors.push(error),
+        }
+
+        targets
+    }
+}
+
+impl Validator for PermissionsValidator {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let targets = self.sensitive_targets(&mut errors);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            for target in targets {
+                let Ok(metadata) = std::fs::metadata(&target.path) else {
+                    continue;
+                };
+                let live = metadata.permissions().mode() & 0o777;
+                let allowed = target
+                    .mode
+                    .unwrap_or(if metadata.is_dir() { 0o700 } else { 0o600 })
+                    & 0o777;
+                if live & !allowed == 0 {
+                    continue;
+                }
+
+                errors.push(
+                    ValidationError::warning(format!(
+                        "{} ({}): {} has mode {:03o}, more permissive than {:03o}",
+                        target.name,
+                        target.id,
+                        target.path.display(),
+                        live,
+                        allowed
+                    ))
+                    .with_fix(format!(
+                        "chmod {:03o} {}",
+                        allowed,
+                        target.path.display()
+                    )),
+                );
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = targets;
+        }
+
+        errors
+    }
+
+    fn name(&self) -> &str {
+        "Sensitive File Permissions"
+    }
+}
+
+fn is_sensitive(home_dir: &Path, target_path: &Path) -> bool {
+    SENSITIVE_PATHS
+        .iter()
+        .any(|sensitive| target_path.starts_with(home_dir.join(sensitive)))
+}
@@ -0,0 +1,97 @@
+use crate::commands::doctor::types::ValidationError;
+use crate::registry::config::ConfigRegistry;
+use crate::registry::encrypted::EncryptedRegistry;
+use crate::registry::package::PackageRegistry;
+use crate::utils::display::with_progress;
+use crate::utils::paths::{
+    get_config_registry_path, get_encrypted_registry_path, get_package_registry_path,
+};
+
+/// Registries loaded once per doctor run and shared by every validator, so
+/// they all see the same snapshot instead of re-reading the files.
+pub(crate) struct LoadedRegistries {
+    config: Result<ConfigRegistry, String>,
+    encrypted: Result<EncryptedRegistry, String>,
+    package: Result<PackageRegistry, String>,
+}
+
+impl LoadedRegistries {
+    /// Entries that fail to expand are reported by the validators rather than
+    /// warned about while loading.
+    pub(crate) fn load() -> Self {
+        with_progress(
+            |_| {},
+            || Self {
+                config: ConfigRegistry::load_or_create(&get_config_registry_path())
+                    .map_err(|e| e.to_string()),
+                encrypted: EncryptedRegistry::load_or_create(&get_encrypted_registry_path())
+                    .map_err(|e| e.to_string()),
+                package: PackageRegistry::load_or_create(&get_package_registry_path())
+                    .map_err(|e| e.to_string()),
+            },
+        )
+    }
+
+    /// Like [`load`](Self::load), but keeping only the entry `id` (treated as
+    /// enabled, so it can be checked before it is switched on). The flag is
+    /// `false` when no registry has it.
+    pub(crate) fn load_entry(id: &str) -> (Self, bool) {
+        let mut loaded = Self::load();
+        let mut found = false;
+        if let Ok(registry) = &mut loaded.config {
+            registry.entries.retain(|entry_id, _| entry_id == id);
+            registry
+                .invalid_entries
+                .retain(|entry_id, _| entry_id == id);
+            found |= !registry.invalid_entries.is_empty();
+            for entry in registry.entries.values_mut() {
+                entry.enabled = true;
+                found = true;
+            }
+        }
+        if let Ok(registry) = &mut loaded.encrypted {
+            registry.entries.retain(|entry_id, _| entry_id == id);
+            registry
+                .invalid_entries
+                .retain(|entry_id, _| entry_id == id);
+            found |= !registry.invalid_entries.is_empty();
+            for entry in registry.entries.values_mut() {
+                entry.enabled = true;
+                found = true;
+            }
+        }
+        if let Ok(registry) = &mut loaded.package {
+            registry.entries.retain(|entry_id, _| entry_id == id);
+            registry
+                .invalid_entries
+                .retain(|entry_id, _| entry_id == id);
+            found |= !registry.invalid_entries.is_empty();
+            for entry in registry.entries.values_mut() {
+                entry.enabled = true;
+                found = true;
+            }
+        }
+        (loaded, found)
+    }
+
+    /// The config registry, or the error a validator should report if it failed to load.
+    pub(crate) fn config(&self) -> Result<&ConfigRegistry, ValidationError> {
+        self.config
+            .as_ref()
+            .map_err(|e| ValidationError::error(format!("Could not load config registry: {}", e)))
+    }
+
+    /// The encrypted registry, or the error a validator should report if it failed to load.
+    pub(crate) fn encrypted(&self) -> Result<&EncryptedRegistry, ValidationError> {
+        self.encrypted.as_ref().map_err(|e| {
+            ValidationError::error(format!("Could not load encrypted config registry: {}", e))
+        })
+    }
+
+    /// The package registry, or the error a validator should report if it failed to load.
+    pub(crate) fn package(&self) -> Result<&PackageRegistry, ValidationError> {
+        self.package
+            .as_ref()
+            .map_err(|e| ValidationError::error(format!("Could not load package registry: {}", e)))
+    }
+}
@@ -1,101 +1,105 @@
+use super::registries::LoadedRegistries;
 use crate::commands::doctor::types::{ValidationError, Validator};
-use crate::registry::config::ConfigRegistry;
+use crate::registry::EntryFilter;
 use crate::registry::package::PackageRegistry;
-use crate::utils::paths::{get_config_registry_path, get_package_registry_path};
 use crate::utils::system::is_command_available;
-use std::collections::HashMap;
-use std::io::ErrorKind;
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
-pub struct RegistryFilesValidator;
+pub struct RegistryFilesValidator {
+    registries: Arc<LoadedRegistries>,
+}
 
-impl Validator for RegistryFilesValidator {
-    fn validate(&self) -> Vec<ValidationError> {
-        let mut errors = Vec::new();
+impl RegistryFilesValidator {
+    pub fn new(registries: Arc<LoadedRegistries>) -> Self {
+        Self { registries }
+    }
 
-        let config_registry_path = get_config_registry_path();
-        match std::fs::read_to_string(&config_registry_path) {
-            Ok(content) => match serde_json::from_str::<ConfigRegistry>(&content) {
-                Ok(registry) => {
-                    let mut source_paths: HashMap<String, Vec<String>> = HashMap::new();
-                    for (id, entry) in registry.entries.iter() {
-                        source_paths
-                            .entry(entry.source_path.clone())
-                            .or_default()
-                            .push(id.clone());
-                    }
-                    for (path, ids) in source_paths {
-                        if ids.len() > 1 {
-                            errors.push(
-                                ValidationError::warning(format!(
-                                    "Duplicate source path '{}' used by: {}",
-                                    path,
-                                    ids.join(", ")
-                                ))
-                                .with_fix("Consider consolidating or renaming entries"),
-                            );
-                        }
-                    }
-                }
-                Err(e) => {
-                    errors.push(ValidationError::error(format!(
-                        "Could not parse config registry: {}",
-                        e
-                    )));
-                }
-            },
-            Err(e) if e.kind() == ErrorKind::NotFound => {
-                errors.push(ValidationError::info(
-                    "Config registry file not found".to_string(),
-                ));
+    fn check_config(&self, errors: &mut Vec<ValidationError>) {
+        let registry = match self.registries.config() {
+            Ok(registry) => registry,
+            Err(error) => {
+                errors.push(error);
+                return;
             }
-            Err(e) => {
-                errors.push(ValidationError::error(format!(
-                    "Could not read config registry: {}",
-                    e
-                )));
+        };
+
+        report_invalid_entries(&registry.invalid_entries, errors);
+
+        let mut source_paths: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (id, entry) in registry.entries.iter() {
+            source_paths
+                .entry(entry.source_path.as_str())
+                .or_default()
+                .push(id.as_str());
+        }
+        for (path, mut ids) in source_paths {
+            if ids.len() > 1 {
+                ids.sort();
+                errors.push(
+                    ValidationError::warning(format!(
+                        "Duplicate source path '{}' used by: {}",
+                        path,
+                        ids.join(", ")
+                    ))
+                    .with_fix("Consider consolidating or renaming entries"),
+                );
             }
         }
+    }
 
-        let package_registry_path = get_package_registry_path();
-        match std::fs::read_to_string(&package_registry_path) {
-            Ok(content) => match serde_json::from_str::<PackageRegistry>(&content) {
-                Ok(registry) => {
-                    let current_platform = PackageRegistry::get_current_platform();
-                    for (id, entry) in registry.get_platform_compatible_entries(&current_platform) {
-                        if !is_command_available(&entry.command) {
-                            errors.push(
-                                ValidationError::info(format!(
-                                    "Package manager '{}' ({}) not found in PATH",
-                                    entry.name, id
-                                ))
-                                .with_fix(format!(
-                                    "Install {} or disable this entry in your profile config",
-                                    entry.command
-                                )),
-                            );
-                        }
-                    }
-                }
-                Err(e) => {
-                    errors.push(ValidationError::error(format!(
-                        "Could not parse package registry: {}",
-                        e
-                    )));
-                }
-            },
-            Err(e) if e.kind() == ErrorKind::NotFound => {
-                errors.push(ValidationError::info(
-                    "Package registry file not found".to_string(),
-                ));
+    fn check_encrypted(&self, errors: &mut Vec<ValidationError>) {
+        match self.registries.encrypted() {
+            Ok(registry) => report_invalid_entries(&registry.invalid_entries, errors),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    fn check_package(&self, errors: &mut Vec<ValidationError>) {
+        let registry = match self.registries.package() {
+            Ok(registry) => registry,
+            Err(error) => {
+                errors.push(error);
+                return;
             }
-            Err(e) => {
-                errors.push(ValidationError::error(format!(
-                    "Could not read package registry: {}",
-                    e
-                )));
+        };
+
+        let current_platform = PackageRegistry::get_current_platform();
+        for (id, entry) in
+            registry.get_platform_compatible_entries(&current_platform, &EntryFilter::default())
+        {
+            if !is_command_available(&entry.command) {
+                errors.push(
+                    ValidationError::info(format!(
+                        "Package manager '{}' ({}) not found in PATH",
+                        entry.name, id
+                    ))
+                    .with_fix(format!(
+                        "Install {} or disable this entry in your profile config",
+                        entry.command
+                    )),
+                );
             }
         }
+    }
+}
+
+/// Entries left out of a registry because their paths do not expand here.
+fn report_invalid_entries(invalid: &BTreeMap<String, String>, errors: &mut Vec<ValidationError>) {
+    for (id, reason) in invalid {
+        errors.push(
+            ValidationError::error(format!("Entry '{}' was skipped: {}", id, reason))
+                .with_fix("Fix or remove the entry's path in the registry"),
+        );
+    }
+}
 
+impl Validator for RegistryFilesValidator {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        self.check_config(&mut errors);
+        self.check_encrypted(&mut errors);
+        self.check_package(&mut errors);
         errors
     }
 
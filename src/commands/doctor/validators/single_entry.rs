@@ -0,0 +1,144 @@
+use super::registries::LoadedRegistries;
+use crate::commands::doctor::types::{ValidationError, Validator};
+use crate::profiles::ActiveProfile;
+use crate::registry::package::PackageRegistry;
+use crate::utils::filesystem::is_writable;
+use crate::utils::system::is_command_available;
+use std::path::Path;
+use std::sync::Arc;
+
+/// End-to-end checks for the one entry `doctor --entry` looks at: whether its
+/// backup resolves, whether restore could write its target, and for package
+/// entries whether the command runs on this platform. The other validators
+/// cover the rest once the registries are narrowed to the entry.
+pub struct SingleEntryValidator {
+    id: String,
+    profile: ActiveProfile,
+    registries: Arc<LoadedRegistries>,
+}
+
+impl SingleEntryValidator {
+    pub fn new(id: String, profile: ActiveProfile, registries: Arc<LoadedRegistries>) -> Self {
+        Self {
+            id,
+            profile,
+            registries,
+        }
+    }
+
+    fn check_config(&self, errors: &mut Vec<ValidationError>) {
+        let Ok(registry) = self.registries.config() else {
+            return;
+        };
+        for (id, entry) in registry.get_enabled_entries() {
+            match self.profile.resolve_source(&entry.source_path) {
+                Some(resolved) => errors.push(ValidationError::info(format!(
+                    "{} ({}): backup resolves to {} ({} layer)",
+                    entry.name,
+                    id,
+                    resolved.path.display(),
+                    resolved.layer
+                ))),
+                None => errors.push(
+                    ValidationError::warning(format!(
+                        "{} ({}): no backup of {} in any layer",
+                        entry.name, id, entry.source_path
+                    ))
+                    .with_fix("Run mntn backup once the target exists"),
+                ),
+            }
+            if !entry.glob {
+                check_writable(&entry.name, id, &entry.target_path, errors);
+            }
+        }
+    }
+
+    fn check_encrypted(&self, errors: &mut Vec<ValidationError>) {
+        let Ok(registry) = self.registries.encrypted() else {
+            return;
+        };
+        for (id, entry) in registry.get_enabled_entries() {
+            check_writable(&entry.name, id, &entry.target_path, errors);
+        }
+    }
+
+    fn check_package(&self, errors: &mut Vec<ValidationError>) {
+        let registry = match self.registries.package() {
+            Ok(registry) => registry,
+            Err(error) => {
+                errors.push(error);
+                return;
+            }
+        };
+        let Some(entry) = registry.entries.get(&self.id) else {
+            return;
+        };
+
+        let platform = PackageRegistry::get_current_platform();
+        if let Some(platforms) = &entry.platforms
+            && !platforms.contains(&platform)
+        {
+            errors.push(ValidationError::info(format!(
+                "{} ({}): only runs on {}, not {}",
+                entry.name,
+                self.id,
+                platforms.join(", "),
+                platform
+            )));
+            return;
+        }
+
+        if is_command_available(&entry.command) {
+            errors.push(ValidationError::info(format!(
+                "{} ({}): {} found in PATH",
+                entry.name, self.id, entry.command
+            )));
+        } else {
+            errors.push(
+                ValidationError::error(format!(
+                    "{} ({}): {} not found in PATH",
+                    entry.name, self.id, entry.command
+                ))
+                .with_fix(format!(
+                    "Install {} or disable entry '{}'",
+                    entry.command, self.id
+                )),
+            );
+        }
+    }
+}
+
+/// Report a target that restore could not write: a read-only file, or no
+/// writable directory to create it in.
+fn check_writable(name: &str, id: &str, target_path: &Path, errors: &mut Vec<ValidationError>) {
+    let existing = target_path.ancestors().find(|path| path.exists());
+    let Some(existing) = existing else {
+        return;
+    };
+    if !is_writable(existing) {
+        errors.push(
+            ValidationError::error(format!(
+                "{} ({}): {} is not writable, so restore cannot update {}",
+                name,
+                id,
+                existing.display(),
+                target_path.display()
+            ))
+            .with_fix("Fix the permissions or point target_path somewhere writable"),
+        );
+    }
+}
+
+impl Validator for SingleEntryValidator {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        self.check_config(&mut errors);
+        self.check_encrypted(&mut errors);
+        self.check_package(&mut errors);
+        errors
+    }
+
+    fn name(&self) -> &str {
+        "Entry"
+    }
+}
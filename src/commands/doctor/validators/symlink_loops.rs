@@ -0,0 +1,141 @@
+use super::registries::LoadedRegistries;
+use crate::commands::doctor::types::{ValidationError, Validator};
+use crate::profiles::ActiveProfile;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Same limit the kernel applies before giving up with `ELOOP`.
+const MAX_LINK_HOPS: usize = 40;
+
+/// Where following a chain of symlinks ends up.
+enum LinkChain {
+    /// The path is not a symlink, or the chain ends at an existing file.
+    Resolves,
+    /// The chain comes back to a link it already visited.
+    Loop(Vec<PathBuf>),
+    /// The chain ends at a path that does not exist.
+    Dangling(PathBuf),
+}
+
+pub struct SymlinkLoopValidator {
+    profile: ActiveProfile,
+    registries: Arc<LoadedRegistries>,
+}
+
+impl SymlinkLoopValidator {
+    pub fn new(profile: ActiveProfile, registries: Arc<LoadedRegistries>) -> Self {
+        Self {
+            profile,
+            registries,
+        }
+    }
+}
+
+impl Validator for SymlinkLoopValidator {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        match self.registries.config() {
+            Ok(registry) => {
+                for (id, entry) in registry.get_enabled_entries() {
+                    check_path(&mut errors, &entry.name, id, "target", &entry.target_path);
+                    for (path, layer) in self.profile.get_candidate_sources(&entry.source_path) {
+                        check_path(
+                            &mut errors,
+                            &entry.name,
+                            id,
+                            &format!("{} backup", layer),
+                            &path,
+                        );
+                    }
+                }
+            }
+            Err(error) => errors.push(error),
+        }
+
+        match self.registries.encrypted() {
+            Ok(registry) => {
+                for (id, entry) in registry.get_enabled_entries() {
+                    check_path(&mut errors, &entry.name, id, "target", &entry.target_path);
+                }
+            }
+            Err(error) => errors.push(error),
+        }
+
+        errors
+    }
+
+    fn name(&self) -> &str {
+        "Symlink Loops"
+    }
+}
+
+fn check_path(errors: &mut Vec<ValidationError>, name: &str, id: &str, role: &str, path: &Path) {
+    match follow_link_chain(path) {
+        LinkChain::Resolves => {}
+        LinkChain::Loop(chain) => {
+            let chain: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+            errors.push(
+                ValidationError::error(format!(
+                    "{} ({}): {} {} is a symlink loop: {}",
+                    name,
+                    id,
+                    role,
+                    path.display(),
+                    chain.join(" -> ")
+                ))
+                .with_fix("Remove the symlink or point it at a real file"),
+            );
+        }
+        LinkChain::Dangling(missing) => {
+            errors.push(
+                ValidationError::error(format!(
+                    "{} ({}): {} {} is a dangling symlink ({} does not exist)",
+                    name,
+                    id,
+                    role,
+                    path.display(),
+                    missing.display()
+                ))
+                .with_fix("Remove the symlink or recreate the file it points to"),
+            );
+        }
+    }
+}
+
+/// Follow `path` link by link, without letting the OS resolve the chain, so
+/// loops and dangling links can be told apart and reported with their path.
+fn follow_link_chain(path: &Path) -> LinkChain {
+    let mut visited = HashSet::new();
+    let mut chain = Vec::new();
+    let mut current = path.to_path_buf();
+
+    loop {
+        let Ok(metadata) = fs::symlink_metadata(&current) else {
+            return if chain.is_empty() {
+                LinkChain::Resolves
+            } else {
+                LinkChain::Dangling(current)
+            };
+        };
+        if !metadata.file_type().is_symlink() {
+            return LinkChain::Resolves;
+        }
+
+        chain.push(current.clone());
+        if !visited.insert(current.clone()) || chain.len() > MAX_LINK_HOPS {
+            return LinkChain::Loop(chain);
+        }
+
+        let Ok(target) = fs::read_link(&current) else {
+            return LinkChain::Resolves;
+        };
+        // Collecting the components drops `.` so `a -> ./a` is seen as a repeat.
+        current = match current.parent() {
+            Some(parent) if target.is_relative() => parent.join(target).components().collect(),
+            _ => target,
+        };
+    }
+}
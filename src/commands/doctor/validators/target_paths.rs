@@ -0,0 +1,105 @@
+use super::registries::LoadedRegistries;
+use crate::commands::doctor::types::{ValidationError, Validator};
+use directories_next::BaseDirs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+pub struct TargetPathsValidator {
+    registries: Arc<LoadedRegistries>,
+}
+
+impl TargetPathsValidator {
+    pub fn new(registries: Arc<LoadedRegistries>) -> Self {
+        Self { registries }
+    }
+}
+
+impl Validator for TargetPathsValidator {
+    fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let Some(base_dirs) = BaseDirs::new() else {
+            return errors;
+        };
+        let home_dir = normalize(base_dirs.home_dir());
+
+        let mut targets: Vec<(String, String, PathBuf, bool)> = Vec::new();
+        match self.registries.config() {
+            Ok(registry) => targets.extend(registry.get_enabled_entries().map(|(id, e)| {
+                (
+                    id.clone(),
+                    e.name.clone(),
+                    e.target_path.clone(),
+                    e.allow_system,
+                )
+            })),
+            Err(error) => errors.push(error),
+        }
+        match self.registries.encrypted() {
+            Ok(registry) => targets.extend(registry.get_enabled_entries().map(|(id, e)| {
+                (
+                    id.clone(),
+                    e.name.clone(),
+                    e.target_path.clone(),
+                    e.allow_system,
+                )
+            })),
+            Err(error) => errors.push(error),
+        }
+
+        for (id, name, target_path, allow_system) in targets {
+            if allow_system {
+                continue;
+            }
+
+            if !target_path.is_absolute() {
+                errors.push(
+                    ValidationError::warning(format!(
+                        "{} ({}): target path {} is relative and depends on the current directory",
+                        name,
+                        id,
+                        target_path.display()
+                    ))
+                    .with_fix("Use an absolute path or one starting with ~"),
+                );
+                continue;
+            }
+
+            if !normalize(&target_path).starts_with(&home_dir) {
+                errors.push(
+                    ValidationError::warning(format!(
+                        "{} ({}): target path {} is outside your home directory; backup and restore would read and overwrite it",
+                        name,
+                        id,
+                        target_path.display()
+                    ))
+                    .with_fix(format!(
+                        "If this is intended, set \"allow_system\": true on entry '{}'",
+                        id
+                    )),
+                );
+            }
+        }
+
+        errors
+    }
+
+    fn name(&self) -> &str {
+        "Target Paths"
+    }
+}
+
+/// Resolve `.` and `..` components without touching the filesystem, so paths
+/// like `~/../../etc/passwd` are judged by where they really point.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
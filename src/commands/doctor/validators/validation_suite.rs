@@ -1,10 +1,17 @@
-use crate::commands::doctor::types::{ValidationReport, Validator};
+use crate::commands::doctor::types::{ValidationError, ValidationReport, Validator};
 use crate::profiles::ActiveProfile;
+use std::sync::Arc;
 
 use super::backup_consistency::BackupConsistencyValidator;
-use super::json_files::JsonFilesValidator;
+use super::config_files::ConfigFilesValidator;
+use super::external_tools::ExternalToolsValidator;
 use super::layer_resolution::LayerResolutionValidator;
+use super::permissions::PermissionsValidator;
+use super::registries::LoadedRegistries;
 use super::registry_files::RegistryFilesValidator;
+use super::single_entry::SingleEntryValidator;
+use super::symlink_loops::SymlinkLoopValidator;
+use super::target_paths::TargetPathsValidator;
 
 pub(crate) struct ValidationSuite {
     validators: Vec<Box<dyn Validator>>,
@@ -12,12 +19,27 @@ pub(crate) struct ValidationSuite {
 
 impl ValidationSuite {
     pub(crate) fn new(profile: ActiveProfile, skip_encrypted: bool, ask_password: bool) -> Self {
+        let registries = Arc::new(LoadedRegistries::load());
         let validators: Vec<Box<dyn Validator>> = vec![
-            Box::new(RegistryFilesValidator),
-            Box::new(LayerResolutionValidator::new(profile.clone())),
-            Box::new(JsonFilesValidator::new(profile.clone())),
+            Box::new(RegistryFilesValidator::new(registries.clone())),
+            Box::new(ExternalToolsValidator::new(registries.clone())),
+            Box::new(TargetPathsValidator::new(registries.clone())),
+            Box::new(PermissionsValidator::new(registries.clone())),
+            Box::new(SymlinkLoopValidator::new(
+                profile.clone(),
+                registries.clone(),
+            )),
+            Box::new(LayerResolutionValidator::new(
+                profile.clone(),
+                registries.clone(),
+            )),
+            Box::new(ConfigFilesValidator::new(
+                profile.clone(),
+                registries.clone(),
+            )),
             Box::new(BackupConsistencyValidator::new(
                 profile.clone(),
+                registries,
                 skip_encrypted,
                 ask_password,
             )),
@@ -25,7 +47,57 @@ impl ValidationSuite {
         Self { validators }
     }
 
-    pub(crate) fn run_all(&self) -> ValidationReport {
+    /// The checks that apply to the single registry entry `id`, which may be
+    /// disabled. Registry-wide checks (registry files, external tools) are
+    /// left out. Returns `None` when no registry has the entry.
+    pub(crate) fn for_entry(
+        profile: ActiveProfile,
+        id: &str,
+        skip_encrypted: bool,
+        ask_password: bool,
+    ) -> Option<Self> {
+        let (registries, found) = LoadedRegistries::load_entry(id);
+        if !found {
+            return None;
+        }
+        let registries = Arc::new(registries);
+        let validators: Vec<Box<dyn Validator>> = vec![
+            Box::new(SingleEntryValidator::new(
+                id.to_string(),
+                profile.clone(),
+                registries.clone(),
+            )),
+            Box::new(TargetPathsValidator::new(registries.clone())),
+            Box::new(PermissionsValidator::new(registries.clone())),
+            Box::new(SymlinkLoopValidator::new(
+                profile.clone(),
+                registries.clone(),
+            )),
+            Box::new(LayerResolutionValidator::new(
+                profile.clone(),
+                registries.clone(),
+            )),
+            Box::new(ConfigFilesValidator::new(
+                profile.clone(),
+                registries.clone(),
+            )),
+            Box::new(BackupConsistencyValidator::new(
+                profile,
+                registries,
+                skip_encrypted,
+                ask_password,
+            )),
+        ];
+        Some(Self { validators })
+    }
+
+    /// Run every validator, one at a time or concurrently. Results are
+    /// reported in the same order either way.
+    pub(crate) fn run_all(&self, parallel: bool) -> ValidationReport {
+        if parallel {
+            return self.run_parallel();
+        }
+
         let mut report = ValidationReport::new();
         for validator in &self.validators {
             let errors = validator.validate();
@@ -33,4 +105,24 @@ impl ValidationSuite {
         }
         report
     }
+
+    fn run_parallel(&self) -> ValidationReport {
+        let results: Vec<Vec<ValidationError>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .validators
+                .iter()
+                .map(|validator| scope.spawn(|| validator.validate()))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("validator thread panicked"))
+                .collect()
+        });
+
+        let mut report = ValidationReport::new();
+        for (validator, errors) in self.validators.iter().zip(results) {
+            report.add_result(validator.name(), errors);
+        }
+        report
+    }
 }
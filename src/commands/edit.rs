@@ -0,0 +1,175 @@
+use crate::cli::EditArgs;
+use crate::commands::core::Command;
+use crate::commands::restore::{RestoreOptions, restore};
+use crate::profiles::{ActiveProfile, ResolvedSource, SourceLayer};
+use crate::registry::config::ConfigRegistry;
+use crate::utils::display::yellow;
+use crate::utils::filesystem::copy_dir_recursive;
+use crate::utils::paths::get_config_registry_path;
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::Path;
+use std::process;
+
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "vi";
+
+struct EditTask {
+    profile: ActiveProfile,
+    id: String,
+    layer: Option<SourceLayer>,
+    apply: bool,
+}
+
+impl EditTask {
+    fn from_args(args: EditArgs) -> Self {
+        Self {
+            profile: ActiveProfile::resolve(args.profile.as_deref()),
+            id: args.id,
+            layer: args.layer,
+            apply: args.apply,
+        }
+    }
+
+    /// The backup copy to edit. Without `--layer` this is whichever copy
+    /// restore would use. With it, a missing copy in that layer is first
+    /// seeded from the one restore would use, so a common file can be
+    /// overridden for a single profile.
+    fn source_to_edit(&self, source_path: &str) -> Result<ResolvedSource> {
+        let resolved = self.profile.resolve_source(source_path);
+
+        let Some(layer) = self.layer else {
+            let Some(resolved) = resolved else {
+                bail!(
+                    "No backup of {} in any layer; run mntn backup first",
+                    source_path
+                );
+            };
+            if resolved.layer == SourceLayer::Common && self.profile.name.is_some() {
+                println!(
+                    "{}",
+                    yellow(&format!(
+                        "   {} has no copy in the profile layer; editing the common copy shared by all profiles (use --layer profile to override it here)",
+                        source_path
+                    ))
+                );
+            }
+            return Ok(resolved);
+        };
+
+        if let Some(existing) = self.profile.resolve_source_in_layer(source_path, layer) {
+            return Ok(existing);
+        }
+        let Some((path, _)) = self
+            .profile
+            .get_candidate_sources(source_path)
+            .into_iter()
+            .find(|(_, candidate_layer)| *candidate_layer == layer)
+        else {
+            bail!("No {} layer to edit (no profile is active)", layer);
+        };
+        let Some(resolved) = resolved else {
+            bail!(
+                "No backup of {} in any layer; run mntn backup first",
+                source_path
+            );
+        };
+
+        seed_copy(&resolved.path, &path)
+            .with_context(|| format!("Copy {} -> {}", resolved.path.display(), path.display()))?;
+        println!(
+            "   Created {} from the {} copy",
+            path.display(),
+            resolved.layer
+        );
+        Ok(ResolvedSource { path, layer })
+    }
+}
+
+/// Copy a backup file or directory into another layer.
+fn seed_copy(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        copy_dir_recursive(from, to, false)?;
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// `$VISUAL`, then `$EDITOR`, then a platform default. The value may carry
+/// arguments, as in `code --wait`.
+fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|parts| !parts.is_empty())
+        .unwrap_or_else(|| vec![DEFAULT_EDITOR.to_string()])
+}
+
+fn open_in_editor(path: &Path) -> Result<()> {
+    let editor = editor_command();
+    let status = process::Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(path)
+        .status()
+        .with_context(|| format!("Start editor {}", editor[0]))?;
+    if !status.success() {
+        bail!("Editor {} exited with {}", editor[0], status);
+    }
+    Ok(())
+}
+
+impl Command for EditTask {
+    fn name(&self) -> &str {
+        "Edit"
+    }
+
+    fn execute(&mut self) -> Result<()> {
+        let registry_path = get_config_registry_path();
+        let registry = ConfigRegistry::load_or_create(&registry_path)
+            .with_context(|| format!("Load config registry: {}", registry_path.display()))?;
+        let Some(entry) = registry.entries.get(&self.id) else {
+            bail!("No config entry with id '{}'", self.id);
+        };
+
+        let source = self.source_to_edit(&entry.source_path)?;
+        println!(
+            "   Editing {} ({} layer)",
+            source.path.display(),
+            source.layer
+        );
+        open_in_editor(&source.path)?;
+
+        if self.apply {
+            let options = RestoreOptions {
+                skip_encrypted: true,
+                layer: self.layer,
+                entries: vec![self.id.clone()],
+                ..RestoreOptions::default()
+            };
+            restore(&self.profile, &options)?;
+        } else {
+            println!(
+                "   Run mntn restore to apply the change to {}",
+                entry.target_path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn task(args: EditArgs) -> Box<dyn Command> {
+    Box::new(EditTask::from_args(args))
+}
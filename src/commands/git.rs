@@ -1,9 +1,11 @@
 use crate::cli::GitArgs;
-use crate::commands::core::{Command, CommandExecutor};
+use crate::commands::core::Command;
+use crate::utils::display::status;
 use crate::utils::paths::get_mntn_dir;
 use crate::utils::system::run_cmd;
 use anyhow::Result;
-use anyhow::bail;
+use anyhow::{Context, bail};
+use chrono::{DateTime, Utc};
 use std::fs;
 use std::path::Path;
 use std::process::{Command as ProcessCommand, Stdio};
@@ -29,8 +31,8 @@ impl Command for GitPassthroughTask {
     }
 }
 
-pub(crate) fn run(args: GitArgs) {
-    CommandExecutor::run(&mut GitPassthroughTask::new(args.args));
+pub(crate) fn task(args: GitArgs) -> Box<dyn Command> {
+    Box::new(GitPassthroughTask::new(args.args))
 }
 
 fn run_git_passthrough(args: Vec<String>) -> Result<()> {
@@ -70,16 +72,120 @@ pub(crate) fn ensure_git_repo(mntn_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Stage every change in the repository and report whether anything is
+/// staged afterwards.
+pub(crate) fn stage_all(repo: &Path) -> Result<bool> {
+    run_cmd("git", &["add", "."], Some(repo))?;
+    has_staged_changes(repo)
+}
+
+/// Commit what is staged, using `message` when it is non-empty and the
+/// timestamped default otherwise. Returns the message used.
+pub(crate) fn commit_staged(repo: &Path, message: Option<&str>) -> Result<String> {
+    let message = commit_message(message);
+    run_cmd("git", &["commit", "-m", &message], Some(repo))?;
+    Ok(message)
+}
+
+pub(crate) fn commit_message(message: Option<&str>) -> String {
+    match message.map(str::trim).filter(|msg| !msg.is_empty()) {
+        Some(msg) => msg.to_string(),
+        None => {
+            let stamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
+            format!("chore: sync mntn ({stamp})")
+        }
+    }
+}
+
+/// Fill in a commit message template. `{hostname}`, `{date}` (UTC),
+/// `{profile}` (`common` without one) and `{changed}` (number of changed
+/// files) are replaced; anything else in braces is kept as written.
+pub(crate) fn render_message_template(
+    template: &str,
+    hostname: &str,
+    date: DateTime<Utc>,
+    profile: Option<&str>,
+    changed: usize,
+) -> String {
+    template
+        .replace("{hostname}", hostname)
+        .replace("{date}", &date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .replace("{profile}", profile.unwrap_or("common"))
+        .replace("{changed}", &changed.to_string())
+}
+
+pub(crate) fn hostname() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .chain(run_cmd("hostname", &[], None).ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Files staged for the next commit.
+pub(crate) fn staged_file_count(repo: &Path) -> Result<usize> {
+    Ok(
+        run_cmd("git", &["diff", "--cached", "--name-only"], Some(repo))?
+            .lines()
+            .count(),
+    )
+}
+
+fn has_staged_changes(repo: &Path) -> Result<bool> {
+    let status = ProcessCommand::new("git")
+        .args(["diff", "--cached", "--quiet"])
+        .current_dir(repo)
+        .status()
+        .context("Checking staged changes")?;
+
+    match status.code() {
+        Some(0) => Ok(false),
+        Some(1) => Ok(true),
+        Some(code) => bail!("git diff --cached --quiet exited with status {}", code),
+        None => bail!("git diff --cached --quiet was terminated by signal"),
+    }
+}
+
+/// The current branch's upstream and how many commits HEAD is `(ahead,
+/// behind)` it, as of the last fetch. `None` when no upstream is configured.
+pub(crate) fn upstream_divergence(repo: &Path) -> Result<Option<(String, usize, usize)>> {
+    let Ok(upstream) = run_cmd(
+        "git",
+        &[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ],
+        Some(repo),
+    ) else {
+        return Ok(None);
+    };
+    let upstream = upstream.trim().to_string();
+    let counts = run_cmd(
+        "git",
+        &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+        Some(repo),
+    )?;
+    let mut counts = counts.split_whitespace().map(|n| n.parse::<usize>());
+    let (Some(Ok(ahead)), Some(Ok(behind))) = (counts.next(), counts.next()) else {
+        bail!("Could not compare HEAD with {}", upstream);
+    };
+    Ok(Some((upstream, ahead, behind)))
+}
+
 pub(crate) fn init_repo_if_missing(mntn_dir: &Path) -> Result<()> {
     if mntn_dir.join(".git").exists() {
         ensure_gitignore_exists(mntn_dir)?;
         return Ok(());
     }
 
-    println!("Initializing git repository in {}", mntn_dir.display());
+    status!("Initializing git repository in {}", mntn_dir.display());
     run_cmd("git", &["init"], Some(mntn_dir))?;
     run_cmd("git", &["branch", "-M", "main"], Some(mntn_dir))?;
-    println!("Git repository initialized");
+    status!("Git repository initialized");
     ensure_gitignore_exists(mntn_dir)?;
     Ok(())
 }
@@ -89,6 +195,9 @@ fn ensure_gitignore_exists(mntn_dir: &Path) -> Result<()> {
     if !gitignore_path.exists() {
         let default_gitignore = "# mntn
 .active-profile
+.lock
+snapshots/
+pre-restore/
 
 # log files
 *.log
@@ -103,7 +212,61 @@ Thumbs.db
 *.swo
 ";
         fs::write(&gitignore_path, default_gitignore)?;
-        println!("Created default .gitignore");
+        status!("Created default .gitignore");
     }
     Ok(())
 }
+
+pub(crate) fn ensure_gitignore_entry(mntn_dir: &Path, entry: &str) -> Result<()> {
+    ensure_gitignore_exists(mntn_dir)?;
+
+    let gitignore_path = mntn_dir.join(".gitignore");
+    let content = fs::read_to_string(&gitignore_path)?;
+    if content.lines().any(|line| line.trim() == entry) {
+        return Ok(());
+    }
+
+    let mut updated = content;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(entry);
+    updated.push('\n');
+    fs::write(&gitignore_path, updated)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn render(template: &str, profile: Option<&str>) -> String {
+        let date = Utc.with_ymd_and_hms(2024, 5, 1, 9, 30, 0).unwrap();
+        render_message_template(template, "laptop", date, profile, 3)
+    }
+
+    #[test]
+    fn fills_in_every_placeholder() {
+        assert_eq!(
+            render(
+                "sync {hostname} {profile} {changed} files at {date}",
+                Some("work")
+            ),
+            "sync laptop work 3 files at 2024-05-01 09:30:00 UTC"
+        );
+    }
+
+    #[test]
+    fn profile_defaults_to_common() {
+        assert_eq!(render("{profile}", None), "common");
+    }
+
+    #[test]
+    fn repeated_and_unknown_placeholders() {
+        assert_eq!(
+            render("{changed}/{changed} {user} {}", None),
+            "3/3 {user} {}"
+        );
+    }
+}
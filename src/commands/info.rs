@@ -0,0 +1,78 @@
+use crate::cli::InfoArgs;
+use crate::commands::core::Command;
+use crate::profiles::{ActiveProfile, ProfileConfig, get_active_profile_with_source};
+use crate::utils::display::yellow;
+use crate::utils::paths::{
+    get_active_profile_path, get_config_registry_path, get_encrypted_registry_path, get_mntn_dir,
+    get_package_registry_path, get_packages_path, get_profiles_config_path, get_settings_path,
+};
+use anyhow::Result;
+use std::path::Path;
+
+struct InfoTask {
+    cli_profile: Option<String>,
+}
+
+impl InfoTask {
+    fn new(cli_profile: Option<String>) -> Self {
+        Self { cli_profile }
+    }
+}
+
+impl Command for InfoTask {
+    fn name(&self) -> &str {
+        "Info"
+    }
+
+    fn execute(&mut self) -> Result<()> {
+        let profile = ActiveProfile::resolve(self.cli_profile.as_deref());
+
+        println!("Profile");
+        match (&self.cli_profile, get_active_profile_with_source()) {
+            (Some(name), _) => println!("   {} (from --profile)", name),
+            (None, Some((name, source))) => println!("   {} (from {})", name, source),
+            (None, None) => println!("   none, using the common layer only"),
+        }
+        if let Some(name) = &profile.name
+            && !ProfileConfig::load_or_default().profile_exists(name)
+        {
+            println!(
+                "{}",
+                yellow(&format!(
+                    "   '{}' is not defined in profiles.json; create it with: mntn profile create {}",
+                    name, name
+                ))
+            );
+        }
+
+        println!();
+        println!("Layers (highest priority first)");
+        if let Some(name) = &profile.name {
+            print_path(&format!("profile {}", name), &profile.get_backup_path());
+        }
+        print_path("common", &ActiveProfile::common_only().get_backup_path());
+        print_path("encrypted", &profile.get_encrypted_backup_path());
+        print_path("packages", &get_packages_path());
+
+        println!();
+        println!("Files");
+        print_path("mntn directory", &get_mntn_dir());
+        print_path("config registry", &get_config_registry_path());
+        print_path("encrypted registry", &get_encrypted_registry_path());
+        print_path("package registry", &get_package_registry_path());
+        print_path("profiles", &get_profiles_config_path());
+        print_path("active profile", &get_active_profile_path());
+        print_path("settings", &get_settings_path());
+
+        Ok(())
+    }
+}
+
+fn print_path(label: &str, path: &Path) {
+    let state = if path.exists() { "" } else { " (missing)" };
+    println!("   {:<20} {}{}", label, path.display(), state);
+}
+
+pub(crate) fn task(args: InfoArgs) -> Box<dyn Command> {
+    Box::new(InfoTask::new(args.profile))
+}
@@ -0,0 +1,37 @@
+use crate::commands::git::ensure_gitignore_entry;
+use crate::errors::AppError;
+use crate::utils::paths::{LOCK_FILE, get_lock_path, get_mntn_dir};
+use anyhow::Result;
+use std::fs::{self, File, TryLockError};
+
+/// Exclusive lock on `~/.mntn/.lock`, held for as long as the value lives.
+/// The OS releases it when the process exits, so a crashed run never leaves a
+/// stale lock behind.
+pub(crate) struct MntnLock {
+    _file: File,
+}
+
+/// Take the lock before a command that writes to `~/.mntn` or the live
+/// targets, failing right away if another mntn process holds it.
+pub(crate) fn acquire() -> Result<MntnLock> {
+    let mntn_dir = get_mntn_dir();
+    fs::create_dir_all(&mntn_dir)?;
+
+    let path = get_lock_path();
+    // The lock file is present while sync runs `git add -A`. New repositories
+    // get the entry from the default .gitignore; older ones get it once, when
+    // the lock file is first created, so a later edit to .gitignore sticks.
+    if !path.exists() && mntn_dir.join(".git").exists() {
+        ensure_gitignore_entry(&mntn_dir, LOCK_FILE)?;
+    }
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(MntnLock { _file: file }),
+        Err(TryLockError::WouldBlock) => Err(AppError::Locked(path).into()),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
@@ -1,9 +1,58 @@
 pub(crate) mod backup;
 pub(crate) mod core;
+pub(crate) mod diff;
 pub(crate) mod doctor;
+pub(crate) mod edit;
 pub(crate) mod git;
+pub(crate) mod info;
+pub(crate) mod lock;
+mod picker;
 pub(crate) mod profile;
 pub(crate) mod restore;
 pub(crate) mod secret;
+pub(crate) mod status;
 pub(crate) mod sync;
 pub(crate) mod r#use;
+
+use crate::cli::{Commands, DoctorActions, ProfileActions};
+use crate::commands::core::Command;
+
+/// Whether `command` may write to `~/.mntn` or the live targets, and so must
+/// hold the [`lock`] while it runs. Previews and read-only commands do not.
+pub(crate) fn needs_lock(command: &Commands) -> bool {
+    match command {
+        Commands::Edit(_) | Commands::Git(_) | Commands::Use(_) => true,
+        Commands::Backup(args) => !args.dry_run,
+        Commands::Restore(args) => !args.dry_run,
+        Commands::Sync(args) => !args.dry_run,
+        Commands::Profile(args) => matches!(
+            args.action,
+            Some(ProfileActions::Create { .. } | ProfileActions::Delete { .. })
+        ),
+        Commands::Doctor(args) => {
+            matches!(&args.action, Some(DoctorActions::Fix(fix)) if !fix.dry_run)
+        }
+        Commands::Diff(_) | Commands::Info(_) | Commands::Status(_) | Commands::Secret { .. } => {
+            false
+        }
+    }
+}
+
+/// Build the task for a parsed subcommand, so every command runs through the
+/// same [`core::CommandExecutor`] path.
+pub(crate) fn dispatch(command: Commands) -> Box<dyn Command> {
+    match command {
+        Commands::Backup(args) => backup::task(args),
+        Commands::Restore(args) => restore::task(args),
+        Commands::Diff(args) => diff::task(args),
+        Commands::Edit(args) => edit::task(args),
+        Commands::Info(args) => info::task(args),
+        Commands::Status(args) => status::task(args),
+        Commands::Use(args) => r#use::task(args),
+        Commands::Profile(args) => profile::task(args),
+        Commands::Git(args) => git::task(args),
+        Commands::Sync(args) => sync::task(args),
+        Commands::Doctor(args) => doctor::task(args),
+        Commands::Secret { action } => secret::task(action),
+    }
+}
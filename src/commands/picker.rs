@@ -0,0 +1,72 @@
+use crate::profiles::{ActiveProfile, SourceLayer};
+use crate::registry::EntryFilter;
+use crate::registry::config::ConfigRegistry;
+use crate::utils::paths::get_config_registry_path;
+use anyhow::{Context, Result, bail};
+use inquire::MultiSelect;
+use inquire::error::InquireError;
+use std::fmt;
+
+/// One config entry in the picker, labelled with the layer its backup
+/// currently resolves from.
+struct PickerOption {
+    id: String,
+    label: String,
+}
+
+impl fmt::Display for PickerOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// Let the user choose which config entries `filter` selects, and return the
+/// ids they left unchecked so the caller can exclude them for this run.
+pub(crate) fn pick_config_entries(
+    profile: &ActiveProfile,
+    filter: &EntryFilter,
+    layer: Option<SourceLayer>,
+    action: &str,
+) -> Result<Vec<String>> {
+    let registry_path = get_config_registry_path();
+    let registry = ConfigRegistry::load_or_create(&registry_path)
+        .with_context(|| format!("Load config registry: {}", registry_path.display()))?;
+
+    let options: Vec<PickerOption> = registry
+        .filtered_entries(filter)
+        .map(|(id, entry)| {
+            let resolved = match layer {
+                Some(layer) => profile.resolve_source_in_layer(&entry.source_path, layer),
+                None => profile.resolve_source(&entry.source_path),
+            };
+            let layer = resolved.map_or("no backup".to_string(), |r| r.layer.to_string());
+            PickerOption {
+                id: id.clone(),
+                label: format!("{} ({}) [{}]", entry.name, id, layer),
+            }
+        })
+        .collect();
+    if options.is_empty() {
+        bail!("No config entries to choose from");
+    }
+    let all_ids: Vec<String> = options.iter().map(|o| o.id.clone()).collect();
+
+    let selected = match MultiSelect::new(&format!("Entries to {}:", action), options)
+        .with_page_size(15)
+        .prompt()
+    {
+        Ok(selected) => selected,
+        Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
+            bail!("Selection cancelled")
+        }
+        Err(e) => return Err(e).context("Show the entry picker"),
+    };
+    if selected.is_empty() {
+        bail!("No entries selected");
+    }
+
+    Ok(all_ids
+        .into_iter()
+        .filter(|id| !selected.iter().any(|o| &o.id == id))
+        .collect())
+}
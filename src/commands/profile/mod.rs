@@ -1,5 +1,5 @@
 use crate::cli::{ProfileActions, ProfileArgs};
-use crate::commands::core::{Command, CommandExecutor};
+use crate::commands::core::Command;
 
 mod create;
 mod delete;
@@ -79,15 +79,13 @@ impl Command for ProfileShowTask {
     }
 }
 
-pub(crate) fn run(args: ProfileArgs) {
+pub(crate) fn task(args: ProfileArgs) -> Box<dyn Command> {
     match args.action {
-        Some(ProfileActions::List) => CommandExecutor::run(&mut ProfileListTask),
+        Some(ProfileActions::List) => Box::new(ProfileListTask),
         Some(ProfileActions::Create { name, description }) => {
-            CommandExecutor::run(&mut ProfileCreateTask::new(name, description));
+            Box::new(ProfileCreateTask::new(name, description))
         }
-        Some(ProfileActions::Delete { name }) => {
-            CommandExecutor::run(&mut ProfileDeleteTask::new(name));
-        }
-        None => CommandExecutor::run(&mut ProfileShowTask),
+        Some(ProfileActions::Delete { name }) => Box::new(ProfileDeleteTask::new(name)),
+        None => Box::new(ProfileShowTask),
     }
 }
@@ -1,19 +1,107 @@
+use crate::registry::config::ConfigRegistryEntry;
 use crate::utils::{
-    display::{red, short_component},
+    display::{red, short_component, warning},
+    filesystem::replace_with_symlink,
+    glob::GlobPattern,
     system::sync_directory_contents,
 };
 use std::fs;
 use std::path::Path;
 
-pub fn restore_configs(backup_path: &Path, target_path: &Path) -> bool {
-    if backup_path.is_dir() {
-        return restore_directory(backup_path, target_path);
+/// Restore a file or directory backup to `target_path`, then apply the
+/// entry's stored `mode` to the target itself when set. Backups kept as
+/// symlinks (`preserve_symlink`) are recreated as the same link.
+pub fn restore_configs(
+    backup_path: &Path,
+    target_path: &Path,
+    entry: &ConfigRegistryEntry,
+    verbose: bool,
+) -> bool {
+    if entry.glob {
+        return restore_glob(backup_path, target_path, entry.mode);
     }
+    if entry.preserve_symlink && backup_path.is_symlink() {
+        return restore_symlink(backup_path, target_path);
+    }
+
+    let restored = if backup_path.is_dir() {
+        restore_directory(backup_path, target_path, verbose)
+    } else {
+        restore_file(backup_path, target_path)
+    };
+
+    match entry.mode {
+        Some(mode) if restored => apply_mode(target_path, mode),
+        _ => restored,
+    }
+}
+
+fn restore_symlink(backup_path: &Path, target_path: &Path) -> bool {
+    let result = fs::read_link(backup_path).and_then(|link_target| {
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        replace_with_symlink(&link_target, target_path)
+    });
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            warning!(
+                "{}",
+                red(&format!(
+                    "Failed to restore symlink {}: {}",
+                    short_component(target_path),
+                    e
+                ))
+            );
+            false
+        }
+    }
+}
+
+/// Restore each backed-up file of a glob entry to the pattern's base
+/// directory, applying `mode` to every file.
+fn restore_glob(backup_path: &Path, pattern: &Path, mode: Option<u32>) -> bool {
+    let glob = GlobPattern::new(pattern);
+    let files = match glob.files_under(backup_path) {
+        Ok(files) => files,
+        Err(e) => {
+            warning!(
+                "{}",
+                red(&format!("Invalid glob {}: {}", pattern.display(), e))
+            );
+            return false;
+        }
+    };
+    if files.is_empty() {
+        warning!(
+            "{}",
+            red(&format!(
+                "No backed-up files match {} in {}",
+                pattern.display(),
+                backup_path.display()
+            ))
+        );
+        return false;
+    }
+
+    let mut restored_all = true;
+    for relative in files {
+        let target_path = glob.base().join(&relative);
+        let restored = restore_file(&backup_path.join(&relative), &target_path);
+        restored_all &= match mode {
+            Some(mode) if restored => apply_mode(&target_path, mode),
+            _ => restored,
+        };
+    }
+    restored_all
+}
 
+fn restore_file(backup_path: &Path, target_path: &Path) -> bool {
     let contents = match fs::read(backup_path) {
         Ok(c) => c,
         Err(e) => {
-            eprintln!(
+            warning!(
                 "{}",
                 red(&format!(
                     "Failed to read backup file {}: {}",
@@ -28,7 +116,7 @@ pub fn restore_configs(backup_path: &Path, target_path: &Path) -> bool {
     if let Some(parent) = target_path.parent()
         && let Err(e) = fs::create_dir_all(parent)
     {
-        eprintln!(
+        warning!(
             "{}",
             red(&format!(
                 "Failed to create directory {}: {}",
@@ -42,7 +130,7 @@ pub fn restore_configs(backup_path: &Path, target_path: &Path) -> bool {
     match fs::write(target_path, contents) {
         Ok(()) => true,
         Err(e) => {
-            eprintln!(
+            warning!(
                 "{}",
                 red(&format!(
                     "Failed to write {}: {}",
@@ -55,9 +143,9 @@ pub fn restore_configs(backup_path: &Path, target_path: &Path) -> bool {
     }
 }
 
-fn restore_directory(backup_path: &Path, target_path: &Path) -> bool {
+fn restore_directory(backup_path: &Path, target_path: &Path, verbose: bool) -> bool {
     if let Err(e) = fs::create_dir_all(target_path) {
-        eprintln!(
+        warning!(
             "{}",
             red(&format!(
                 "Failed to create target directory {}: {}",
@@ -68,10 +156,10 @@ fn restore_directory(backup_path: &Path, target_path: &Path) -> bool {
         return false;
     }
 
-    match sync_directory_contents(backup_path, target_path) {
+    match sync_directory_contents(backup_path, target_path, verbose) {
         Ok(()) => true,
         Err(e) => {
-            eprintln!(
+            warning!(
                 "{}",
                 red(&format!(
                     "Failed to restore directory {}: {}",
@@ -83,3 +171,27 @@ fn restore_directory(backup_path: &Path, target_path: &Path) -> bool {
         }
     }
 }
+
+fn apply_mode(target_path: &Path, mode: u32) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = fs::set_permissions(target_path, fs::Permissions::from_mode(mode)) {
+            warning!(
+                "{}",
+                red(&format!(
+                    "Failed to set mode {:o} on {}: {}",
+                    mode,
+                    short_component(target_path),
+                    e
+                ))
+            );
+            return false;
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (target_path, mode);
+    }
+    true
+}
@@ -2,22 +2,30 @@ use crate::encryption::{
     create_temp_path, decrypt_file, get_encrypted_path, load_tar_member_map,
     set_private_file_permissions,
 };
-use crate::profiles::ActiveProfile;
+use crate::profiles::{ActiveProfile, ResolvedSource, SourceLayer};
+use crate::registry::EntryFilter;
 use crate::registry::encrypted::{EncryptedRegistry, EncryptedRegistryEntry};
 use crate::utils::{
-    display::{green, red, short_component, yellow},
-    paths::get_encrypted_registry_path,
+    display::{entry_status, green, red, short_component, status, warning, yellow},
+    paths::{ENCRYPTED_BUNDLE_FILE, get_encrypted_registry_path, reroot_path},
 };
 use age::secrecy::SecretString;
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
 
-pub fn restore_encrypted_configs(profile: &ActiveProfile, password: &SecretString) -> (u32, u32) {
+pub fn restore_encrypted_configs(
+    profile: &ActiveProfile,
+    password: &SecretString,
+    target_root: Option<&Path>,
+    layer: Option<SourceLayer>,
+    filter: &EntryFilter,
+) -> (u32, u32) {
     let encrypted_registry_path = get_encrypted_registry_path();
     let encrypted_registry = match EncryptedRegistry::load_or_create(&encrypted_registry_path) {
         Ok(registry) => registry,
         Err(e) => {
-            eprintln!(
+            warning!(
                 "Failed to load encrypted registry, skipping encrypted restore: {}",
                 e
             );
@@ -26,28 +34,34 @@ pub fn restore_encrypted_configs(profile: &ActiveProfile, password: &SecretStrin
     };
 
     let enabled_entries: Vec<_> = encrypted_registry
-        .get_enabled_entries()
-        .map(|(id, e)| (id.clone(), e.clone()))
+        .filtered_entries(filter)
+        .map(|(id, e)| {
+            let mut entry = e.clone();
+            if let Some(root) = target_root {
+                entry.target_path = reroot_path(root, &entry.target_path);
+            }
+            (id.clone(), entry)
+        })
         .collect();
 
     if enabled_entries.is_empty() {
-        println!("No encrypted configuration files found to restore");
+        status!("No encrypted configuration files found to restore");
         return (0, 0);
     }
 
-    println!("   Encrypted configs: {} entries", enabled_entries.len());
+    status!("   Encrypted configs: {} entries", enabled_entries.len());
 
-    if let Some(bundle) = profile.resolve_encrypted_bundle()
+    if let Some(bundle) = resolve_encrypted(profile, ENCRYPTED_BUNDLE_FILE, layer)
         && bundle.path.is_file()
     {
         let tar_temp = match create_temp_path("enc-restore-tar") {
             Ok(p) => p,
             Err(e) => {
-                eprintln!(
+                warning!(
                     "Could not create temp file for bundle restore: {}, using per-file backups",
                     e
                 );
-                return restore_encrypted_legacy(profile, password, enabled_entries);
+                return restore_encrypted_legacy(profile, password, enabled_entries, layer);
             }
         };
 
@@ -58,7 +72,7 @@ pub fn restore_encrypted_configs(profile: &ActiveProfile, password: &SecretStrin
                     return restore_from_bundle_members(&enabled_entries, &members);
                 }
                 Err(e) => {
-                    eprintln!(
+                    warning!(
                         "Could not read encrypted bundle archive: {}, trying per-file backups",
                         e
                     );
@@ -66,7 +80,7 @@ pub fn restore_encrypted_configs(profile: &ActiveProfile, password: &SecretStrin
                 }
             },
             Err(e) => {
-                eprintln!(
+                warning!(
                     "Could not decrypt {} ({}), trying per-file backups",
                     bundle.path.display(),
                     e
@@ -76,7 +90,7 @@ pub fn restore_encrypted_configs(profile: &ActiveProfile, password: &SecretStrin
         }
     }
 
-    restore_encrypted_legacy(profile, password, enabled_entries)
+    restore_encrypted_legacy(profile, password, enabled_entries, layer)
 }
 
 fn restore_from_bundle_members(
@@ -95,7 +109,7 @@ fn restore_from_bundle_members(
                 if let Some(parent) = target_path.parent()
                     && let Err(e) = fs::create_dir_all(parent)
                 {
-                    eprintln!(
+                    warning!(
                         "{}",
                         red(&format!(
                             "Failed to create directory {}: {}",
@@ -109,7 +123,7 @@ fn restore_from_bundle_members(
                 match fs::write(target_path, contents) {
                     Ok(()) => {
                         if let Err(e) = set_private_file_permissions(target_path) {
-                            eprintln!(
+                            warning!(
                                 "{}",
                                 red(&format!(
                                     "Failed to set permissions on {}: {}",
@@ -118,10 +132,10 @@ fn restore_from_bundle_members(
                             );
                         }
                         restored_count += 1;
-                        println!("     {} {}", green("✔"), entry.source_path);
+                        entry_status!("     {} {}", green("✔"), entry.source_path);
                     }
                     Err(e) => {
-                        eprintln!(
+                        warning!(
                             "{}",
                             red(&format!(
                                 "Failed to write {} ({}): {}",
@@ -133,7 +147,7 @@ fn restore_from_bundle_members(
                 }
             }
             None => {
-                println!(
+                warning!(
                     "{}",
                     yellow(&format!(
                         "     skipped {} ({}): not in encrypted bundle",
@@ -148,10 +162,23 @@ fn restore_from_bundle_members(
     (restored_count, skipped_count)
 }
 
+/// Resolve an encrypted source across all layers, or only in `layer` when given.
+fn resolve_encrypted(
+    profile: &ActiveProfile,
+    source_path: &str,
+    layer: Option<SourceLayer>,
+) -> Option<ResolvedSource> {
+    match layer {
+        Some(layer) => profile.resolve_encrypted_source_in_layer(source_path, layer),
+        None => profile.resolve_encrypted_source(source_path),
+    }
+}
+
 fn restore_encrypted_legacy(
     profile: &ActiveProfile,
     password: &SecretString,
     enabled_entries: Vec<(String, EncryptedRegistryEntry)>,
+    layer: Option<SourceLayer>,
 ) -> (u32, u32) {
     let mut restored_count = 0;
     let mut skipped_count = 0;
@@ -161,12 +188,12 @@ fn restore_encrypted_legacy(
         let target_label = short_component(target_path);
         let encrypted_path = get_encrypted_path(&entry.source_path);
 
-        match profile.resolve_encrypted_source(&encrypted_path) {
+        match resolve_encrypted(profile, &encrypted_path, layer) {
             Some(resolved) => {
                 if let Some(parent) = target_path.parent()
                     && let Err(e) = fs::create_dir_all(parent)
                 {
-                    eprintln!(
+                    warning!(
                         "{}",
                         red(&format!(
                             "Failed to create directory {}: {}",
@@ -180,10 +207,10 @@ fn restore_encrypted_legacy(
                 match decrypt_file(&resolved.path, target_path, password) {
                     Ok(()) => {
                         restored_count += 1;
-                        println!("     {} {}", green("✔"), entry.source_path);
+                        entry_status!("     {} {}", green("✔"), entry.source_path);
                     }
                     Err(e) => {
-                        eprintln!(
+                        warning!(
                             "{}",
                             red(&format!(
                                 "Failed to decrypt {} ({}): {}",
@@ -195,7 +222,7 @@ fn restore_encrypted_legacy(
                 }
             }
             None => {
-                println!(
+                warning!(
                     "{}",
                     yellow(&format!(
                         "     skipped {} ({}): no encrypted backup in any layer",
@@ -1,102 +1,410 @@
 use crate::commands::core::Command;
+use crate::commands::picker;
 use crate::encryption::resolve_encryption_password;
-use crate::profiles::ActiveProfile;
-use crate::registry::config::ConfigRegistry;
+use crate::profiles::{ActiveProfile, ResolvedSource, SourceLayer};
+use crate::registry::EntryFilter;
+use crate::registry::config::{ConfigRegistry, ConfigRegistryEntry};
+use crate::registry::encrypted::EncryptedRegistry;
 use crate::utils::{
-    display::{green, yellow},
-    paths::get_config_registry_path,
+    display::{
+        Progress, entry_status, green, hide_entry_lines, print_progress, status, warning,
+        with_progress, yellow,
+    },
+    glob::GlobPattern,
+    paths::{get_config_registry_path, get_encrypted_registry_path, reroot_path},
+    system::run_hook,
 };
+use pre_restore::LiveTarget;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 mod config;
 mod encrypted;
+mod pre_restore;
 
-struct RestoreTask {
-    profile: ActiveProfile,
-    skip_encrypted: bool,
-    ask_password: bool,
+/// What a [`restore`] run does. `Default` matches `mntn restore` with no flags.
+#[derive(Debug, Clone, Default)]
+pub struct RestoreOptions {
+    /// Leave encrypted configs alone (no password prompt).
+    pub skip_encrypted: bool,
+    /// Prompt for the encryption password instead of using the keychain.
+    pub ask_password: bool,
+    /// Restore under this directory instead of the real targets.
+    pub target_root: Option<PathBuf>,
+    /// Delete live targets whose entries have no backup in any layer.
+    pub prune: bool,
+    /// Report what would happen without writing or deleting anything.
+    pub dry_run: bool,
+    /// Restore only from this layer.
+    pub layer: Option<SourceLayer>,
+    /// Registry entry ids to leave out of this run.
+    pub exclude_entries: Vec<String>,
+    /// Restore only these registry entry ids (all entries when empty).
+    pub entries: Vec<String>,
+    /// Print each rsync command and let its output through.
+    pub verbose: bool,
+    /// Copy every live target into `~/.mntn/pre-restore/<timestamp>/` first.
+    pub backup_first: bool,
 }
 
-impl RestoreTask {
-    fn new(profile: ActiveProfile, skip_encrypted: bool, ask_password: bool) -> Self {
-        Self {
-            profile,
-            skip_encrypted,
-            ask_password,
-        }
+/// Entry counts from a [`restore`] run. In a dry run, `restored` counts the
+/// entries that would be restored.
+#[derive(Debug, Clone, Default)]
+pub struct RestoreReport {
+    pub restored: u32,
+    pub skipped: u32,
+    /// Every progress line of the run, in order. Left empty by
+    /// [`restore_with_progress`], which hands them out as they happen.
+    pub progress: Vec<Progress>,
+}
+
+/// Restore configs (and encrypted configs) for `profile`, the same way
+/// `mntn restore` does. Nothing is printed; progress is collected in
+/// [`RestoreReport::progress`].
+pub fn restore(profile: &ActiveProfile, options: &RestoreOptions) -> anyhow::Result<RestoreReport> {
+    let progress = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&progress);
+    let mut report = restore_with_progress(profile, options, move |line| {
+        sink.borrow_mut().push(line.clone())
+    })?;
+    report.progress = progress.take();
+    Ok(report)
+}
+
+/// [`restore`], calling `on_progress` with each progress line as the run
+/// goes instead of collecting them.
+pub fn restore_with_progress(
+    profile: &ActiveProfile,
+    options: &RestoreOptions,
+    on_progress: impl Fn(&Progress) + 'static,
+) -> anyhow::Result<RestoreReport> {
+    with_progress(on_progress, || RestoreRun { profile, options }.run())
+}
+
+struct RestoreRun<'a> {
+    profile: &'a ActiveProfile,
+    options: &'a RestoreOptions,
+}
+
+/// Run a registry hook for one entry, reporting a failure without stopping
+/// the run.
+fn run_entry_hook(kind: &str, hook: &str, id: &str, target_path: &Path) {
+    match run_hook(hook, target_path) {
+        Ok(_) => entry_status!("       ran {} hook", kind),
+        Err(e) => warning!(
+            "{}",
+            yellow(&format!("     {} hook for {} failed: {:#}", kind, id, e))
+        ),
     }
 }
 
-impl Command for RestoreTask {
-    fn name(&self) -> &str {
-        "Restore"
+impl RestoreRun<'_> {
+    /// Delete live targets whose entries have no backup in any layer, making
+    /// the backup the authoritative state. Only entries still in the registry
+    /// are considered, since their target paths are the only ones known.
+    fn prune_targets(&self, orphaned: &[(&String, PathBuf)]) {
+        if orphaned.is_empty() {
+            status!("   Prune: nothing to remove");
+            return;
+        }
+
+        status!("   Prune: {} targets without a backup", orphaned.len());
+        for (id, target_path) in orphaned {
+            if self.options.dry_run {
+                entry_status!("     would remove {} ({})", target_path.display(), id);
+                continue;
+            }
+
+            let result = if target_path.is_dir() && !target_path.is_symlink() {
+                fs::remove_dir_all(target_path)
+            } else {
+                fs::remove_file(target_path)
+            };
+            match result {
+                Ok(()) => entry_status!("     {} removed {}", green("✔"), target_path.display()),
+                Err(e) => warning!(
+                    "{}",
+                    yellow(&format!(
+                        "     failed to remove {} ({}): {}",
+                        target_path.display(),
+                        id,
+                        e
+                    ))
+                ),
+            }
+        }
     }
 
-    fn execute(&mut self) -> anyhow::Result<()> {
+    /// The only layer `entry` may be restored from, if any: `--layer`, or the
+    /// profile layer for `profile_required` entries.
+    fn entry_layer(&self, entry: &ConfigRegistryEntry) -> Option<SourceLayer> {
+        if entry.profile_required {
+            Some(SourceLayer::Profile)
+        } else {
+            self.options.layer
+        }
+    }
+
+    fn resolve_source(&self, entry: &ConfigRegistryEntry) -> Option<ResolvedSource> {
+        if entry.profile_required && self.options.layer == Some(SourceLayer::Common) {
+            return None;
+        }
+        match self.entry_layer(entry) {
+            Some(layer) => self
+                .profile
+                .resolve_source_in_layer(&entry.source_path, layer),
+            None => self.profile.resolve_source(&entry.source_path),
+        }
+    }
+
+    fn target_path(&self, target_path: &std::path::Path) -> PathBuf {
+        match &self.options.target_root {
+            Some(root) => reroot_path(root, target_path),
+            None => target_path.to_path_buf(),
+        }
+    }
+
+    fn run(&self) -> anyhow::Result<RestoreReport> {
         let config_registry_path = get_config_registry_path();
         let config_registry = ConfigRegistry::load_or_create(&config_registry_path)?;
 
-        let enabled_entries: Vec<_> = config_registry.get_enabled_entries().collect();
-        println!(
+        let filter = EntryFilter {
+            include_disabled: false,
+            excluded: self.options.exclude_entries.clone(),
+            only: self.options.entries.clone(),
+        };
+        let enabled_entries: Vec<_> = config_registry.filtered_entries(&filter).collect();
+        status!(
             "   Configurations: {} entries ({})",
             enabled_entries.len(),
             self.profile
         );
+        if let Some(root) = &self.options.target_root {
+            status!("   Target root: {}", root.display());
+        }
+        if let Some(layer) = self.options.layer {
+            status!("   Layer: {} only", layer);
+        }
+        if !filter.excluded.is_empty() {
+            status!("   Excluding: {}", filter.excluded.join(", "));
+        }
+        if !filter.only.is_empty() {
+            status!("   Only: {}", filter.only.join(", "));
+        }
+
+        if self.options.dry_run {
+            status!("   Dry run: nothing will be written or deleted");
+        }
+
+        if self.options.backup_first && !self.options.dry_run {
+            let mut targets: Vec<LiveTarget> = enabled_entries
+                .iter()
+                .map(|(_, entry)| LiveTarget {
+                    relative: PathBuf::from(&entry.source_path),
+                    target_path: self.target_path(&entry.target_path),
+                    glob: entry.glob,
+                    private: false,
+                })
+                .collect();
+            if !self.options.skip_encrypted
+                && let Ok(registry) =
+                    EncryptedRegistry::load_or_create(&get_encrypted_registry_path())
+            {
+                targets.extend(
+                    registry
+                        .filtered_entries(&filter)
+                        .map(|(_, entry)| LiveTarget {
+                            relative: PathBuf::from("encrypted").join(&entry.source_path),
+                            target_path: self.target_path(&entry.target_path),
+                            glob: false,
+                            private: true,
+                        }),
+                );
+            }
+            pre_restore::save_live_targets(&targets)?;
+        }
 
         let mut restored_count = 0;
         let mut skipped_count = 0;
+        let mut orphaned: Vec<(&String, PathBuf)> = Vec::new();
 
         for (id, entry) in enabled_entries {
-            let target_path = &entry.target_path;
-            match self.profile.resolve_source(&entry.source_path) {
+            let target_path = self.target_path(&entry.target_path);
+            match self.resolve_source(entry) {
+                Some(resolved) if self.options.dry_run => {
+                    restored_count += 1;
+                    entry_status!(
+                        "     would restore {} -> {} ({})",
+                        entry.source_path,
+                        target_path.display(),
+                        resolved.layer
+                    );
+                }
                 Some(resolved) => {
-                    if config::restore_configs(&resolved.path, target_path) {
+                    if config::restore_configs(
+                        &resolved.path,
+                        &target_path,
+                        entry,
+                        self.options.verbose,
+                    ) {
                         restored_count += 1;
-                        println!("     {} {}", green("✔"), entry.source_path);
+                        entry_status!("     {} {}", green("✔"), entry.source_path);
+                        // Hooks act on the real files, so a run under
+                        // --target-root leaves them out.
+                        // A glob entry restores many files, so its hook runs
+                        // once with the pattern's base directory as the target.
+                        if let Some(hook) = &entry.post_restore
+                            && self.options.target_root.is_none()
+                        {
+                            let hook_target = if entry.glob {
+                                GlobPattern::new(&target_path).base().to_path_buf()
+                            } else {
+                                target_path.clone()
+                            };
+                            run_entry_hook("post_restore", hook, id, &hook_target);
+                        }
                     } else {
                         skipped_count += 1;
                     }
                 }
                 None => {
-                    println!(
+                    let reason = if entry.profile_required && self.profile.name.is_none() {
+                        "profile_required, but no profile is active".to_string()
+                    } else if entry.profile_required {
+                        "profile_required, and no copy in the profile layer".to_string()
+                    } else {
+                        match self.options.layer {
+                            Some(layer) => format!("no backup in the {} layer", layer),
+                            None => "no backup in any layer".to_string(),
+                        }
+                    };
+                    warning!(
                         "{}",
                         yellow(&format!(
-                            "     skipped {} ({}): no backup in any layer",
-                            entry.source_path, id
+                            "     skipped {} ({}): {}",
+                            entry.source_path, id, reason
                         ))
                     );
+                    for (path, layer) in self.profile.get_candidate_sources(&entry.source_path) {
+                        if self.entry_layer(entry).is_none_or(|only| only == layer) {
+                            entry_status!("       checked {} ({})", path.display(), layer);
+                        }
+                    }
                     skipped_count += 1;
+                    // With --layer, a copy in the other layer still counts as a backup.
+                    if target_path.symlink_metadata().is_ok()
+                        && self.profile.resolve_source(&entry.source_path).is_none()
+                    {
+                        orphaned.push((id, target_path));
+                    }
                 }
             }
         }
 
-        if !self.skip_encrypted {
-            match resolve_encryption_password(self.ask_password, false) {
+        if self.options.prune {
+            self.prune_targets(&orphaned);
+        }
+
+        if self.options.dry_run {
+            if !self.options.skip_encrypted {
+                status!("   Encrypted configs are not previewed in a dry run");
+            }
+        } else if !self.options.skip_encrypted {
+            match resolve_encryption_password(self.options.ask_password, false) {
                 Ok(password) => {
                     let (encrypted_restored, encrypted_skipped) =
-                        encrypted::restore_encrypted_configs(&self.profile, &password);
+                        encrypted::restore_encrypted_configs(
+                            self.profile,
+                            &password,
+                            self.options.target_root.as_deref(),
+                            self.options.layer,
+                            &filter,
+                        );
                     restored_count += encrypted_restored;
                     skipped_count += encrypted_skipped;
                 }
                 Err(e) => {
-                    eprintln!("Skipping encrypted restore: {}", e);
+                    warning!("Skipping encrypted restore: {}", e);
                 }
             }
         }
 
-        println!(
-            "Restore complete. {} restored, {} skipped",
-            restored_count, skipped_count
-        );
+        if self.options.dry_run {
+            status!(
+                "Dry run complete. {} would be restored, {} skipped",
+                restored_count,
+                skipped_count
+            );
+        } else {
+            status!(
+                "Restore complete. {} restored, {} skipped",
+                restored_count,
+                skipped_count
+            );
+        }
+
+        Ok(RestoreReport {
+            restored: restored_count,
+            skipped: skipped_count,
+            progress: Vec::new(),
+        })
+    }
+}
 
+struct RestoreTask {
+    profile: ActiveProfile,
+    options: RestoreOptions,
+    interactive: bool,
+    summary_only: bool,
+}
+
+impl RestoreTask {
+    fn from_args(args: crate::cli::RestoreArgs) -> Self {
+        Self {
+            profile: args.resolve_profile(),
+            options: RestoreOptions {
+                skip_encrypted: args.skip_encrypted,
+                ask_password: args.ask_password,
+                target_root: args.target_root,
+                prune: args.prune,
+                dry_run: args.dry_run,
+                layer: args.layer,
+                exclude_entries: args.exclude_entries,
+                entries: Vec::new(),
+                verbose: args.verbose,
+                backup_first: args.backup_first,
+            },
+            interactive: args.interactive,
+            summary_only: args.summary_only,
+        }
+    }
+}
+
+impl Command for RestoreTask {
+    fn name(&self) -> &str {
+        "Restore"
+    }
+
+    fn execute(&mut self) -> anyhow::Result<()> {
+        if self.summary_only {
+            hide_entry_lines();
+        }
+        if self.interactive {
+            let filter = EntryFilter {
+                include_disabled: false,
+                excluded: self.options.exclude_entries.clone(),
+                only: self.options.entries.clone(),
+            };
+            let unselected =
+                picker::pick_config_entries(&self.profile, &filter, self.options.layer, "restore")?;
+            self.options.exclude_entries.extend(unselected);
+        }
+        restore_with_progress(&self.profile, &self.options, print_progress)?;
         Ok(())
     }
 }
 
-pub(crate) fn run(args: crate::cli::RestoreArgs) {
-    use crate::commands::core::CommandExecutor;
-    let profile = args.resolve_profile();
-    CommandExecutor::run(&mut RestoreTask::new(
-        profile,
-        args.skip_encrypted,
-        args.ask_password,
-    ));
+pub(crate) fn task(args: crate::cli::RestoreArgs) -> Box<dyn Command> {
+    Box::new(RestoreTask::from_args(args))
 }
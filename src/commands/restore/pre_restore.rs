@@ -0,0 +1,111 @@
+use crate::commands::git::ensure_gitignore_entry;
+use crate::encryption::set_private_file_permissions;
+use crate::utils::display::status;
+use crate::utils::filesystem::{copy_dir_recursive, replace_with_symlink};
+use crate::utils::glob::GlobPattern;
+use crate::utils::paths::{PRE_RESTORE_DIR, get_mntn_dir, get_pre_restore_path};
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A live target that restore is about to overwrite.
+pub(super) struct LiveTarget {
+    /// Where the copy goes, relative to the pre-restore directory.
+    pub relative: PathBuf,
+    pub target_path: PathBuf,
+    pub glob: bool,
+    /// Copies of encrypted entries are made readable by the owner only.
+    pub private: bool,
+}
+
+/// Copy every live target into `~/.mntn/pre-restore/<timestamp>/`, laid out
+/// like the backup layer, so a bad restore can be undone by hand. Targets
+/// that do not exist yet are left out. Returns the directory written.
+pub(super) fn save_live_targets(targets: &[LiveTarget]) -> Result<PathBuf> {
+    let pre_restore_path = get_pre_restore_path();
+    fs::create_dir_all(&pre_restore_path).with_context(|| {
+        format!(
+            "Create pre-restore directory {}",
+            pre_restore_path.display()
+        )
+    })?;
+    ensure_gitignore_entry(&get_mntn_dir(), &format!("{}/", PRE_RESTORE_DIR))?;
+
+    let stamp = Utc::now().format("%Y-%m-%dT%H-%M-%SZ").to_string();
+    let destination = pre_restore_path.join(&stamp);
+    if destination.exists() {
+        bail!("Pre-restore copy {} already exists", stamp);
+    }
+    fs::create_dir(&destination)
+        .with_context(|| format!("Create pre-restore directory {}", destination.display()))?;
+
+    let mut saved = 0;
+    for target in targets {
+        let copy_path = destination.join(&target.relative);
+        let copied = copy_live_target(target, &copy_path).with_context(|| {
+            format!(
+                "Copy {} -> {}",
+                target.target_path.display(),
+                copy_path.display()
+            )
+        })?;
+        if copied {
+            saved += 1;
+        }
+    }
+
+    status!(
+        "   Saved {} live targets to {}",
+        saved,
+        destination.display()
+    );
+    Ok(destination)
+}
+
+/// Copy one target as it is on disk: symlinks stay links, directories are
+/// copied without following the links inside them. Returns `false` when
+/// there is nothing to copy.
+fn copy_live_target(target: &LiveTarget, copy_path: &Path) -> Result<bool> {
+    let target_path = &target.target_path;
+
+    if target.glob {
+        let glob = GlobPattern::new(target_path);
+        let files = glob.files_under(glob.base())?;
+        for relative in &files {
+            copy_file(
+                &glob.base().join(relative),
+                &copy_path.join(relative),
+                false,
+            )?;
+        }
+        return Ok(!files.is_empty());
+    }
+
+    let Ok(metadata) = fs::symlink_metadata(target_path) else {
+        return Ok(false);
+    };
+    if let Some(parent) = copy_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if metadata.file_type().is_symlink() {
+        replace_with_symlink(&fs::read_link(target_path)?, copy_path)?;
+    } else if metadata.is_dir() {
+        fs::create_dir_all(copy_path)?;
+        copy_dir_recursive(target_path, copy_path, false)?;
+    } else {
+        copy_file(target_path, copy_path, target.private)?;
+    }
+    Ok(true)
+}
+
+fn copy_file(source: &Path, destination: &Path, private: bool) -> Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(source, destination)?;
+    if private {
+        set_private_file_permissions(destination)?;
+    }
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use crate::cli::SecretActions;
-use crate::commands::core::{Command, CommandExecutor};
+use crate::commands::core::Command;
 use crate::encryption::{clear_stored_encryption_password, persist_encryption_password};
 use anyhow::Result;
 
@@ -27,9 +27,9 @@ impl Command for SecretDeleteTask {
     }
 }
 
-pub(crate) fn run(action: SecretActions) {
+pub(crate) fn task(action: SecretActions) -> Box<dyn Command> {
     match action {
-        SecretActions::Set => CommandExecutor::run(&mut SecretSetTask),
-        SecretActions::Delete => CommandExecutor::run(&mut SecretDeleteTask),
+        SecretActions::Set => Box::new(SecretSetTask),
+        SecretActions::Delete => Box::new(SecretDeleteTask),
     }
 }
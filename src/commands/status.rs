@@ -0,0 +1,373 @@
+use crate::cli::StatusArgs;
+use crate::commands::backup::manifest::load_manifest;
+use crate::commands::core::Command;
+use crate::commands::doctor::ValidationSuite;
+use crate::commands::git;
+use crate::profiles::{ActiveProfile, ProfileSource, get_active_profile_with_source};
+use crate::registry::EntryFilter;
+use crate::registry::config::ConfigRegistry;
+use crate::registry::encrypted::EncryptedRegistry;
+use crate::registry::package::PackageRegistry;
+use crate::utils::display::{green, red, yellow};
+use crate::utils::format::duration_to_human_readable;
+use crate::utils::paths::{
+    get_config_registry_path, get_encrypted_registry_path, get_mntn_dir, get_package_registry_path,
+};
+use crate::utils::system::run_cmd;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+/// Bumped whenever a field of `status --json` is renamed, removed, or changes
+/// meaning. New fields may be added without a bump.
+const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Everything `mntn status` reports, gathered before it is printed so the
+/// text and `--json` output always agree.
+#[derive(Serialize)]
+struct StatusReport {
+    schema_version: u32,
+    profile: ProfileStatus,
+    registries: RegistriesStatus,
+    last_backup: Option<LastBackup>,
+    /// `None` when `~/.mntn` is not a git repository.
+    git: Option<GitStatus>,
+    doctor: DoctorStatus,
+}
+
+#[derive(Serialize)]
+struct ProfileStatus {
+    /// `None` when only the common layer is in use.
+    name: Option<String>,
+    /// `cli`, `env`, `file`, `settings`, or `none`.
+    source: &'static str,
+    /// Where the name came from, as shown in the text output.
+    #[serde(skip)]
+    origin: String,
+}
+
+#[derive(Serialize)]
+struct RegistriesStatus {
+    configs: RegistryStatus,
+    packages: RegistryStatus,
+    encrypted: RegistryStatus,
+}
+
+/// Entry counts for one registry. `total` counts every entry for configs and
+/// encrypted configs, and only the current platform's entries for packages.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum RegistryStatus {
+    Loaded { enabled: usize, total: usize },
+    Failed { error: String },
+}
+
+#[derive(Serialize)]
+struct LastBackup {
+    created_at: String,
+    age_seconds: u64,
+}
+
+/// The `~/.mntn` repository, or why it could not be read.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum GitStatus {
+    Repository {
+        branch: String,
+        /// `false` on an unborn branch, e.g. right after `mntn git init`.
+        has_commits: bool,
+        uncommitted_changes: usize,
+        upstream: Option<UpstreamStatus>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+struct UpstreamStatus {
+    name: String,
+    ahead: usize,
+    behind: usize,
+}
+
+#[derive(Serialize)]
+struct DoctorStatus {
+    errors: usize,
+    warnings: usize,
+}
+
+struct StatusTask {
+    cli_profile: Option<String>,
+    json: bool,
+}
+
+impl StatusTask {
+    fn new(cli_profile: Option<String>, json: bool) -> Self {
+        Self { cli_profile, json }
+    }
+
+    fn collect(&self) -> Result<StatusReport> {
+        let profile = ActiveProfile::resolve(self.cli_profile.as_deref());
+
+        let profile_status = match (&self.cli_profile, get_active_profile_with_source()) {
+            (Some(name), _) => ProfileStatus {
+                name: Some(name.clone()),
+                source: "cli",
+                origin: "--profile".to_string(),
+            },
+            (None, Some((name, source))) => ProfileStatus {
+                name: Some(name),
+                source: match source {
+                    ProfileSource::Environment => "env",
+                    ProfileSource::File => "file",
+                    ProfileSource::Settings => "settings",
+                },
+                origin: source.to_string(),
+            },
+            (None, None) => ProfileStatus {
+                name: None,
+                source: "none",
+                origin: String::new(),
+            },
+        };
+
+        let report = ValidationSuite::new(profile.clone(), true, false).run_all(true);
+
+        Ok(StatusReport {
+            schema_version: JSON_SCHEMA_VERSION,
+            profile: profile_status,
+            registries: collect_registries(),
+            last_backup: collect_last_backup(&profile),
+            git: collect_git(),
+            doctor: DoctorStatus {
+                errors: report.error_count(),
+                warnings: report.warning_count(),
+            },
+        })
+    }
+
+    fn print(&self, report: &StatusReport) {
+        println!("Profile");
+        match &report.profile.name {
+            Some(name) => println!("   {} (from {})", name, report.profile.origin),
+            None => println!("   none, using the common layer only"),
+        }
+
+        println!("Registries");
+        let platform = format!(" for {}", PackageRegistry::get_current_platform());
+        for (label, status, suffix) in [
+            ("configs", &report.registries.configs, ""),
+            ("packages", &report.registries.packages, platform.as_str()),
+            ("encrypted", &report.registries.encrypted, ""),
+        ] {
+            match status {
+                RegistryStatus::Loaded { enabled, total } => println!(
+                    "   {:<12} {} enabled of {}{}",
+                    label, enabled, total, suffix
+                ),
+                RegistryStatus::Failed { error } => {
+                    println!("{}", red(&format!("   {:<12} {}", label, error)))
+                }
+            }
+        }
+
+        println!("Last backup");
+        match report
+            .last_backup
+            .as_ref()
+            .and_then(|backup| DateTime::parse_from_rfc3339(&backup.created_at).ok())
+        {
+            Some(created_at) => {
+                let created_at = created_at.with_timezone(&Utc);
+                let age = (Utc::now() - created_at).to_std().unwrap_or_default();
+                println!(
+                    "   {} ({} ago)",
+                    created_at.format("%Y-%m-%d %H:%M UTC"),
+                    duration_to_human_readable(age)
+                );
+            }
+            None => println!(
+                "{}",
+                yellow("   unknown (no manifest in the backup layer; run mntn backup)")
+            ),
+        }
+
+        println!("Git");
+        match &report.git {
+            Some(GitStatus::Repository {
+                branch,
+                has_commits,
+                uncommitted_changes,
+                upstream,
+            }) => {
+                println!(
+                    "   branch {}{}, {} uncommitted change(s)",
+                    branch,
+                    if *has_commits {
+                        ""
+                    } else {
+                        " (no commits yet)"
+                    },
+                    uncommitted_changes
+                );
+                match upstream {
+                    Some(upstream) => println!(
+                        "   {}: {} ahead, {} behind (as of the last fetch)",
+                        upstream.name, upstream.ahead, upstream.behind
+                    ),
+                    None => println!("   no upstream branch configured"),
+                }
+            }
+            Some(GitStatus::Failed { error }) => println!("{}", red(&format!("   {}", error))),
+            None => println!("{}", yellow("   not a git repository (run mntn backup)")),
+        }
+
+        println!("Doctor");
+        let DoctorStatus { errors, warnings } = report.doctor;
+        let summary = format!(
+            "   {} error(s), {} warning(s) (encrypted files not checked)",
+            errors, warnings
+        );
+        if errors > 0 {
+            println!(
+                "{}",
+                red(&format!("{}; run mntn doctor for details", summary))
+            );
+        } else if warnings > 0 {
+            println!(
+                "{}",
+                yellow(&format!("{}; run mntn doctor for details", summary))
+            );
+        } else {
+            println!("{}", green(&summary));
+        }
+    }
+}
+
+fn collect_registries() -> RegistriesStatus {
+    let configs = match ConfigRegistry::load_or_create(&get_config_registry_path()) {
+        Ok(registry) => RegistryStatus::Loaded {
+            enabled: registry.get_enabled_entries().count(),
+            total: registry.entries.len(),
+        },
+        Err(e) => RegistryStatus::Failed {
+            error: e.to_string(),
+        },
+    };
+    let packages = match PackageRegistry::load_or_create(&get_package_registry_path()) {
+        Ok(registry) => {
+            let platform = PackageRegistry::get_current_platform();
+            let all = EntryFilter {
+                include_disabled: true,
+                ..EntryFilter::default()
+            };
+            RegistryStatus::Loaded {
+                enabled: registry
+                    .get_platform_compatible_entries(&platform, &EntryFilter::default())
+                    .count(),
+                total: registry
+                    .get_platform_compatible_entries(&platform, &all)
+                    .count(),
+            }
+        }
+        Err(e) => RegistryStatus::Failed {
+            error: e.to_string(),
+        },
+    };
+    let encrypted = match EncryptedRegistry::load_or_create(&get_encrypted_registry_path()) {
+        Ok(registry) => RegistryStatus::Loaded {
+            enabled: registry.get_enabled_entries().count(),
+            total: registry.entries.len(),
+        },
+        Err(e) => RegistryStatus::Failed {
+            error: e.to_string(),
+        },
+    };
+    RegistriesStatus {
+        configs,
+        packages,
+        encrypted,
+    }
+}
+
+fn collect_last_backup(profile: &ActiveProfile) -> Option<LastBackup> {
+    let manifest = load_manifest(&profile.get_backup_path()).ok().flatten()?;
+    let created_at = DateTime::parse_from_rfc3339(&manifest.created_at).ok()?;
+    let age = (Utc::now() - created_at.with_timezone(&Utc))
+        .to_std()
+        .unwrap_or_default();
+    Some(LastBackup {
+        created_at: manifest.created_at,
+        age_seconds: age.as_secs(),
+    })
+}
+
+/// The repository state, `None` when `~/.mntn` is not a repository. A git
+/// failure is reported in the section rather than failing the whole status.
+fn collect_git() -> Option<GitStatus> {
+    let repo = get_mntn_dir();
+    if !repo.join(".git").exists() {
+        return None;
+    }
+    Some(
+        read_git_status(&repo).unwrap_or_else(|e| GitStatus::Failed {
+            error: format!("{:#}", e).trim_end().to_string(),
+        }),
+    )
+}
+
+fn read_git_status(repo: &Path) -> Result<GitStatus> {
+    // symbolic-ref also names an unborn branch, which rev-parse cannot;
+    // rev-parse covers a detached HEAD.
+    let branch = run_cmd("git", &["symbolic-ref", "--short", "HEAD"], Some(repo))
+        .or_else(|_| run_cmd("git", &["rev-parse", "--abbrev-ref", "HEAD"], Some(repo)))?;
+    let has_commits = run_cmd(
+        "git",
+        &["rev-parse", "--verify", "--quiet", "HEAD"],
+        Some(repo),
+    )
+    .is_ok();
+    let uncommitted_changes = run_cmd("git", &["status", "--porcelain"], Some(repo))?
+        .lines()
+        .count();
+    let upstream = if has_commits {
+        git::upstream_divergence(repo)?.map(|(name, ahead, behind)| UpstreamStatus {
+            name,
+            ahead,
+            behind,
+        })
+    } else {
+        None
+    };
+    Ok(GitStatus::Repository {
+        branch: branch.trim().to_string(),
+        has_commits,
+        uncommitted_changes,
+        upstream,
+    })
+}
+
+impl Command for StatusTask {
+    fn name(&self) -> &str {
+        "Status"
+    }
+
+    fn execute(&mut self) -> Result<()> {
+        let report = self.collect()?;
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            self.print(&report);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn task(args: StatusArgs) -> Box<dyn Command> {
+    if args.json {
+        crate::utils::display::send_status_to_stderr();
+    }
+    Box::new(StatusTask::new(args.profile, args.json))
+}
@@ -1,52 +1,321 @@
-use crate::cli::SyncArgs;
-use crate::commands::core::{Command, CommandExecutor};
-use crate::commands::git::run_cmd_passthrough;
-use crate::utils::display::yellow;
+use crate::cli::{SyncArgs, SyncStrategy};
+use crate::commands::core::{Command, ExitCodeError};
+use crate::commands::git;
+use crate::profiles::ActiveProfile;
+use crate::utils::display::{red, yellow};
 use crate::utils::paths::get_mntn_dir;
-use crate::utils::system::run_cmd;
+use crate::utils::system::{confirm, run_cmd};
 use anyhow::{Context, Result, bail};
 use chrono::Utc;
 use std::path::Path;
-use std::process::Command as ProcessCommand;
+use std::process::{Command as ProcessCommand, ExitStatus, Stdio};
+use std::time::Duration;
+
+/// Failures `mntn sync` reports with their own exit status, so scripts can
+/// react without scraping git output. Anything else exits with 1.
+#[derive(Debug, Clone, Copy)]
+enum SyncFailure {
+    /// `--pull` left merge conflicts in the repository.
+    PullConflict,
+    /// The remote has commits this repository lacks (non-fast-forward).
+    PushRejected,
+    /// The remote could not be reached or refused the credentials.
+    Remote,
+}
+
+impl SyncFailure {
+    fn exit_code(self) -> i32 {
+        match self {
+            SyncFailure::PullConflict => 2,
+            SyncFailure::PushRejected => 3,
+            SyncFailure::Remote => 4,
+        }
+    }
+
+    fn classify(stderr: &str) -> Option<Self> {
+        const REJECTED: &[&str] = &["[rejected]", "non-fast-forward", "fetch first"];
+        const REMOTE: &[&str] = &[
+            "Authentication failed",
+            "Permission denied",
+            "Could not read from remote repository",
+            "Could not resolve host",
+            "unable to access",
+            "Connection refused",
+            "Connection timed out",
+            "does not appear to be a git repository",
+            "No configured push destination",
+        ];
+
+        if contains_any(stderr, REJECTED) {
+            Some(SyncFailure::PushRejected)
+        } else if contains_any(stderr, REMOTE) {
+            Some(SyncFailure::Remote)
+        } else {
+            None
+        }
+    }
+
+    /// Whether `stderr` looks like a network hiccup that may succeed on a
+    /// retry. Rejections, conflicts, and authentication errors never do.
+    fn is_transient(stderr: &str) -> bool {
+        const TRANSIENT: &[&str] = &[
+            "Could not resolve host",
+            "Connection refused",
+            "Connection timed out",
+            "Connection reset",
+            "Operation timed out",
+            "the remote end hung up unexpectedly",
+            "early EOF",
+            "Temporary failure in name resolution",
+        ];
+        const PERMANENT: &[&str] = &["Authentication failed", "Permission denied"];
+
+        contains_any(stderr, TRANSIENT)
+            && !contains_any(stderr, PERMANENT)
+            && !matches!(Self::classify(stderr), Some(SyncFailure::PushRejected))
+    }
+}
+
+/// Case-insensitive, since git has changed the capitalization of some
+/// messages between releases.
+fn contains_any(stderr: &str, markers: &[&str]) -> bool {
+    let stderr = stderr.to_lowercase();
+    markers
+        .iter()
+        .any(|marker| stderr.contains(&marker.to_lowercase()))
+}
 
 struct SyncTask {
+    profile: ActiveProfile,
     message: Option<String>,
+    message_template: Option<String>,
+    pull: bool,
+    strategy: Option<SyncStrategy>,
+    preview: bool,
+    yes: bool,
+    dry_run: bool,
+    retries: u32,
 }
 
 impl SyncTask {
-    fn new(message: Option<String>) -> Self {
-        Self { message }
-    }
-
-    fn commit_message(&self) -> Result<String> {
-        match self
-            .message
-            .as_ref()
-            .map(|msg| msg.trim())
-            .filter(|msg| !msg.is_empty())
-        {
-            Some(msg) => Ok(msg.to_string()),
-            None => {
-                let stamp = Utc::now().format("%Y-%m-%d %H:%M:%S UTC");
-                Ok(format!("chore: sync mntn ({stamp})"))
+    fn new(args: SyncArgs) -> Self {
+        Self {
+            profile: args.resolve_profile(),
+            message: args.message,
+            message_template: args.message_template,
+            pull: args.pull,
+            strategy: args.strategy,
+            preview: args.preview,
+            yes: args.yes,
+            dry_run: args.dry_run,
+            retries: args.retries,
+        }
+    }
+
+    /// `--message`, else `--message-template` filled in for `changed` files,
+    /// else the timestamped default.
+    fn commit_message(&self, changed: usize) -> String {
+        match (&self.message, &self.message_template) {
+            (None, Some(template)) => git::render_message_template(
+                template,
+                &git::hostname(),
+                Utc::now(),
+                self.profile.name.as_deref(),
+                changed,
+            ),
+            _ => git::commit_message(self.message.as_deref()),
+        }
+    }
+
+    /// Report what a sync would do. Only `git fetch` touches anything (the
+    /// remote-tracking refs); nothing is staged, committed, merged, or pushed.
+    fn dry_run(&self, repo: &Path) -> Result<()> {
+        println!("   Dry run: nothing will be committed, pulled, or pushed");
+
+        let status = run_cmd("git", &["status", "--porcelain"], Some(repo))?;
+        let changes: Vec<&str> = status.lines().collect();
+        if changes.is_empty() {
+            println!("{}", yellow("   No changes to commit"));
+        } else {
+            println!(
+                "   Would commit {} changed file(s) as \"{}\":",
+                changes.len(),
+                self.commit_message(changes.len())
+            );
+            for line in &changes {
+                println!("     {}", line);
             }
         }
+
+        let (status, stderr) = self.run_remote_retrying(repo, &["fetch"])?;
+        if !status.success() {
+            return Err(Self::remote_failure("fetch", status, &stderr));
+        }
+
+        let Some((upstream, ahead, behind)) = git::upstream_divergence(repo)? else {
+            println!("   No upstream branch is configured; git push would decide where to push");
+            return Ok(());
+        };
+        let ahead = ahead + usize::from(!changes.is_empty());
+        println!(
+            "   {}: {} commit(s) behind, {} to push",
+            upstream, behind, ahead
+        );
+
+        let mut ahead = ahead;
+        if behind > 0 {
+            if self.pull {
+                println!(
+                    "   Would pull (merge) {} commit(s) from {}",
+                    behind, upstream
+                );
+                // Diverged histories are joined by a merge commit; otherwise it fast-forwards.
+                if ahead > 0 {
+                    ahead += 1;
+                }
+            } else {
+                println!(
+                    "{}",
+                    yellow("   Push would be rejected: the remote has new commits (use --pull)")
+                );
+                return Ok(());
+            }
+        }
+        if ahead > 0 {
+            println!("   Would push {} commit(s) to {}", ahead, upstream);
+        } else {
+            println!("   Nothing to push");
+        }
+        Ok(())
+    }
+
+    /// Print the staged change summary and the commit message and, unless
+    /// `--yes` was given, ask whether to commit it. Declining unstages
+    /// everything again.
+    fn preview_staged(&self, repo: &Path, message: &str) -> Result<bool> {
+        let stat = run_cmd("git", &["diff", "--cached", "--stat"], Some(repo))?;
+        println!("   Changes to commit as \"{}\":", message);
+        for line in stat.lines() {
+            println!("     {}", line);
+        }
+
+        if self.yes {
+            return Ok(true);
+        }
+
+        let accepted = confirm("   Commit and push these changes?")
+            .map_err(|e| anyhow::anyhow!("{}; pass --yes to skip the prompt", e))?;
+        if !accepted {
+            run_cmd("git", &["reset", "-q"], Some(repo))?;
+        }
+        Ok(accepted)
     }
 
-    fn has_staged_changes(repo: &Path) -> Result<bool> {
-        let status = ProcessCommand::new("git")
-            .args(["diff", "--cached", "--quiet"])
+    /// Run a git command that talks to the remote, capturing stderr so it can
+    /// be echoed and then used to pick the exit status on failure.
+    fn run_remote(repo: &Path, args: &[&str]) -> Result<(ExitStatus, String)> {
+        let output = ProcessCommand::new("git")
+            .args(args)
             .current_dir(repo)
-            .status()
-            .context("Checking staged changes")?;
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::piped())
+            .output()
+            .with_context(|| format!("Running git {}", args.join(" ")))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        eprint!("{}", stderr);
+        Ok((output.status, stderr))
+    }
 
-        match status.code() {
-            Some(0) => Ok(false),
-            Some(1) => Ok(true),
-            Some(code) => bail!("git diff --cached --quiet exited with status {}", code),
-            None => bail!("git diff --cached --quiet was terminated by signal"),
+    /// [`run_remote`](Self::run_remote), retried up to `--retries` times with
+    /// exponential backoff (1s, 2s, 4s, ...) while failures look transient.
+    fn run_remote_retrying(&self, repo: &Path, args: &[&str]) -> Result<(ExitStatus, String)> {
+        let mut attempt = 0;
+        loop {
+            let (status, stderr) = Self::run_remote(repo, args)?;
+            if status.success() || attempt >= self.retries || !SyncFailure::is_transient(&stderr) {
+                return Ok((status, stderr));
+            }
+            attempt += 1;
+            let delay = Duration::from_secs(1 << (attempt - 1).min(6));
+            eprintln!(
+                "{}",
+                yellow(&format!(
+                    "   git {} hit a network error; retrying in {}s ({} of {})",
+                    args[0],
+                    delay.as_secs(),
+                    attempt,
+                    self.retries
+                ))
+            );
+            std::thread::sleep(delay);
+        }
+    }
+
+    fn remote_failure(command: &str, status: ExitStatus, stderr: &str) -> anyhow::Error {
+        let message = format!("git {} failed ({})", command, status);
+        match SyncFailure::classify(stderr) {
+            Some(failure) => ExitCodeError::new(failure.exit_code(), message).into(),
+            None => anyhow::anyhow!(message),
         }
     }
+
+    fn pull(&self, repo: &Path) -> Result<()> {
+        let mut args = vec!["pull", "--no-rebase", "--no-edit"];
+        match self.strategy {
+            Some(SyncStrategy::Ours) => args.extend(["-X", "ours"]),
+            Some(SyncStrategy::Theirs) => args.extend(["-X", "theirs"]),
+            None => {}
+        }
+
+        let (status, stderr) = self.run_remote_retrying(repo, &args)?;
+
+        let conflicts = Self::conflicted_paths(repo)?;
+        if !conflicts.is_empty() {
+            eprintln!("{}", red("   Merge conflicts in ~/.mntn:"));
+            for path in &conflicts {
+                eprintln!("{}", red(&format!("     {}", path)));
+            }
+            eprintln!("   To resolve:");
+            eprintln!("     1. Edit the files above and remove the conflict markers");
+            eprintln!("     2. mntn git add <file> for each, then mntn git commit --no-edit");
+            eprintln!("     3. Run mntn sync again to push");
+            eprintln!("   Or undo the pull with: mntn git merge --abort");
+            return Err(ExitCodeError::new(
+                SyncFailure::PullConflict.exit_code(),
+                format!(
+                    "Pull left {} conflicted file(s); push skipped",
+                    conflicts.len()
+                ),
+            )
+            .into());
+        }
+
+        if !status.success() {
+            return Err(Self::remote_failure("pull", status, &stderr));
+        }
+        Ok(())
+    }
+
+    /// Paths git reports as unmerged (`UU`, `AA`, `DU`, ...) in the work tree.
+    fn conflicted_paths(repo: &Path) -> Result<Vec<String>> {
+        const UNMERGED: &[&str] = &["DD", "AU", "UD", "UA", "DU", "AA", "UU"];
+        let status = run_cmd("git", &["status", "--porcelain"], Some(repo))?;
+        Ok(status
+            .lines()
+            .filter(|line| line.len() > 3 && UNMERGED.contains(&&line[..2]))
+            .map(|line| line[3..].to_string())
+            .collect())
+    }
+
+    fn push(&self, repo: &Path) -> Result<()> {
+        let (status, stderr) = self.run_remote_retrying(repo, &["push"])?;
+        if status.success() {
+            return Ok(());
+        }
+        Err(Self::remote_failure("push", status, &stderr))
+    }
 }
 
 impl Command for SyncTask {
@@ -56,23 +325,115 @@ impl Command for SyncTask {
 
     fn execute(&mut self) -> anyhow::Result<()> {
         let repo_dir = get_mntn_dir();
-        crate::commands::git::ensure_git_repo(&repo_dir)?;
+        git::ensure_git_repo(&repo_dir)?;
+        if self.dry_run {
+            return self.dry_run(&repo_dir);
+        }
 
-        run_cmd("git", &["add", "."], Some(&repo_dir))?;
-        let staged = Self::has_staged_changes(&repo_dir)?;
+        let staged = git::stage_all(&repo_dir)?;
         if staged {
-            let message = self.commit_message()?;
-            run_cmd("git", &["commit", "-m", &message], Some(&repo_dir))?;
+            let message = self.commit_message(git::staged_file_count(&repo_dir)?);
+            if self.preview && !self.preview_staged(&repo_dir, &message)? {
+                bail!("Sync cancelled; changes were unstaged and nothing was committed or pushed");
+            }
+            git::commit_staged(&repo_dir, Some(&message))?;
         } else {
             println!("{}", yellow("   No changes to commit"));
         }
 
-        run_cmd_passthrough("git", &["push"], Some(&repo_dir))?;
+        if self.pull {
+            self.pull(&repo_dir)?;
+        }
+
+        self.push(&repo_dir)?;
         Ok(())
     }
 }
 
-pub(crate) fn run(args: SyncArgs) {
-    let mut task = SyncTask::new(args.message);
-    CommandExecutor::run(&mut task);
+pub(crate) fn task(args: SyncArgs) -> Box<dyn Command> {
+    Box::new(SyncTask::new(args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUSH_FETCH_FIRST: &str = "To github.com:me/dotfiles.git
+ ! [rejected]        main -> main (fetch first)
+error: failed to push some refs to 'github.com:me/dotfiles.git'
+hint: Updates were rejected because the remote contains work that you do
+hint: not have locally.";
+    const PUSH_NON_FAST_FORWARD: &str = "To https://github.com/me/dotfiles.git
+ ! [rejected]        main -> main (non-fast-forward)
+error: failed to push some refs to 'https://github.com/me/dotfiles.git'";
+    const HTTPS_AUTH: &str = "remote: Invalid username or token.
+fatal: Authentication failed for 'https://github.com/me/dotfiles.git/'";
+    const SSH_PUBLICKEY: &str = "git@github.com: Permission denied (publickey).
+fatal: Could not read from remote repository.
+
+Please make sure you have the correct access rights
+and the repository exists.";
+    const SSH_DNS: &str =
+        "ssh: Could not resolve hostname github.com: Temporary failure in name resolution
+fatal: Could not read from remote repository.";
+    const HTTPS_DNS: &str = "fatal: unable to access 'https://github.com/me/dotfiles.git/': Could not resolve host: github.com";
+    const SSH_TIMEOUT: &str = "ssh: connect to host github.com port 22: Connection timed out
+fatal: Could not read from remote repository.";
+    const HUNG_UP: &str = "error: RPC failed; curl 56 GnuTLS recv error (-9): A TLS packet with unexpected length was received.
+fatal: the remote end hung up unexpectedly";
+    const NO_REMOTE: &str = "fatal: 'origin' does not appear to be a git repository
+fatal: Could not read from remote repository.";
+    const UNRELATED: &str = "error: pathspec 'x' did not match any file(s) known to git";
+
+    fn classify(stderr: &str) -> Option<i32> {
+        SyncFailure::classify(stderr).map(SyncFailure::exit_code)
+    }
+
+    #[test]
+    fn rejected_pushes_exit_with_3() {
+        assert_eq!(classify(PUSH_FETCH_FIRST), Some(3));
+        assert_eq!(classify(PUSH_NON_FAST_FORWARD), Some(3));
+    }
+
+    #[test]
+    fn remote_and_auth_errors_exit_with_4() {
+        for stderr in [
+            HTTPS_AUTH,
+            SSH_PUBLICKEY,
+            SSH_DNS,
+            HTTPS_DNS,
+            SSH_TIMEOUT,
+            NO_REMOTE,
+        ] {
+            assert_eq!(classify(stderr), Some(4), "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn other_errors_are_not_classified() {
+        assert_eq!(classify(UNRELATED), None);
+        assert_eq!(classify(""), None);
+        assert_eq!(SyncFailure::PullConflict.exit_code(), 2);
+    }
+
+    #[test]
+    fn network_errors_are_retried() {
+        for stderr in [SSH_DNS, HTTPS_DNS, SSH_TIMEOUT, HUNG_UP] {
+            assert!(SyncFailure::is_transient(stderr), "{}", stderr);
+        }
+    }
+
+    #[test]
+    fn rejections_and_auth_errors_are_not_retried() {
+        for stderr in [
+            PUSH_FETCH_FIRST,
+            PUSH_NON_FAST_FORWARD,
+            HTTPS_AUTH,
+            SSH_PUBLICKEY,
+            NO_REMOTE,
+            UNRELATED,
+        ] {
+            assert!(!SyncFailure::is_transient(stderr), "{}", stderr);
+        }
+    }
 }
@@ -1,5 +1,5 @@
 use crate::cli::UseArgs;
-use crate::commands::core::{Command, CommandExecutor};
+use crate::commands::core::Command;
 use crate::profiles::{
     ProfileConfig, clear_active_profile, get_active_profile_name, set_active_profile,
 };
@@ -59,7 +59,6 @@ impl Command for UseTask {
     }
 }
 
-pub(crate) fn run(args: UseArgs) {
-    let mut task = UseTask::new(args.profile);
-    CommandExecutor::run(&mut task);
+pub(crate) fn task(args: UseArgs) -> Box<dyn Command> {
+    Box::new(UseTask::new(args.profile))
 }
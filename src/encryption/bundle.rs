@@ -12,7 +12,14 @@ pub(crate) fn normalize_tar_path(path: &Path) -> String {
         .to_string()
 }
 
-pub(crate) fn write_entries_tar(tar_path: &Path, entries: &[(&str, &Path)]) -> Result<()> {
+/// Write `entries` (archive path, file on disk) into a tar, followed by
+/// `kept` (archive path, contents), members carried over from an earlier
+/// bundle.
+pub(crate) fn write_entries_tar(
+    tar_path: &Path,
+    entries: &[(&str, &Path)],
+    kept: &[(&str, &[u8])],
+) -> Result<()> {
     let file = fs::File::create(tar_path)
         .with_context(|| format!("Create tar archive {}", tar_path.display()))?;
     let mut builder = tar::Builder::new(file);
@@ -29,6 +36,14 @@ pub(crate) fn write_entries_tar(tar_path: &Path, entries: &[(&str, &Path)]) -> R
             .append_file(*source_path, &mut input)
             .with_context(|| format!("Append {} to tar", source_path))?;
     }
+    for (source_path, data) in kept {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o600);
+        builder
+            .append_data(&mut header, *source_path, *data)
+            .with_context(|| format!("Append {} to tar", source_path))?;
+    }
     builder.finish().context("Finish tar archive")?;
     Ok(())
 }
@@ -1,5 +1,6 @@
 mod bundle;
 
+use crate::utils::display::warning;
 use age::secrecy::ExposeSecret;
 use age::secrecy::SecretString;
 use anyhow::{Context, Result, bail};
@@ -103,7 +104,7 @@ pub(crate) fn resolve_encryption_password(
     }
     let password = prompt_password(confirm_on_prompt)?;
     if !had_stored {
-        eprintln!(
+        warning!(
             "Tip: run `mntn secret set` to save this password in your system keychain and skip prompts later."
         );
     }
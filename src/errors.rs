@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 pub(crate) type Result<T> = std::result::Result<T, AppError>;
@@ -10,10 +11,27 @@ pub(crate) enum AppError {
     Json(#[from] serde_json::Error),
     #[error(transparent)]
     Utf8(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    Toml(#[from] toml::de::Error),
     #[error("Command '{cmd}' failed with status {status:?}: {stderr}")]
     CommandFailure {
         cmd: String,
         status: Option<i32>,
         stderr: String,
     },
+    #[error("Failed to load registry {}: {error}", path.display())]
+    RegistryLoad { path: PathBuf, error: Box<AppError> },
+    #[error("Failed to load settings {}: {error}", path.display())]
+    SettingsLoad { path: PathBuf, error: Box<AppError> },
+    #[error(
+        "another mntn process is running (lock held on {}); try again once it finishes",
+        .0.display()
+    )]
+    Locked(PathBuf),
+    #[error("rsync is not installed; it is required to copy directory entries")]
+    RsyncMissing,
+    #[error("Registry version {found} is newer than supported version {supported}; upgrade mntn")]
+    UnsupportedRegistryVersion { found: String, supported: String },
+    #[error("Cannot expand ~{0}: no home directory for that user in /etc/passwd")]
+    UnknownUser(String),
 }
@@ -4,26 +4,95 @@ mod encryption;
 mod errors;
 mod profiles;
 mod registry;
+mod settings;
 mod utils;
 
+pub use commands::backup::{
+    BackupOptions, BackupReport, EditedBackup, backup, backup_with_progress,
+};
+pub use commands::restore::{RestoreOptions, RestoreReport, restore, restore_with_progress};
+pub use profiles::{ActiveProfile, SourceLayer};
+pub use utils::display::{Progress, ProgressKind};
+
 use clap::{CommandFactory, Parser};
-use cli::{Cli, Commands};
-use commands::{backup, doctor, git, profile, restore, secret, sync, r#use};
+use cli::Cli;
+use commands::core::CommandExecutor;
+use settings::Settings;
+use std::ffi::OsString;
+use utils::display::red;
+
+/// Parse the process arguments and run the selected command, returning the
+/// exit status.
+pub fn run() -> i32 {
+    run_from(std::env::args_os())
+}
 
-pub fn run() {
-    let cli = Cli::parse();
+/// The name and one-line description of every command [`run_from`] accepts,
+/// in the order `mntn --help` lists them.
+pub fn commands() -> Vec<(String, String)> {
+    Cli::command()
+        .get_subcommands()
+        .map(|command| {
+            let about = command.get_about().map(ToString::to_string);
+            (command.get_name().to_string(), about.unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Parse `args` (including the binary name, as with `std::env::args_os`) and
+/// run the selected command. Lets front-ends drive mntn without a subprocess:
+/// it never exits the process, and returns the status the `mntn` binary
+/// would exit with (2 for invalid arguments, as clap reports them).
+///
+/// Each call starts from a clean slate: `--mntn-dir`, the `config.toml`
+/// profile and output settings such as `--summary-only` do not carry over to
+/// the next call. Calls from several threads at once share these settings,
+/// so run them one at a time.
+pub fn run_from<I, T>(args: I) -> i32
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return e.exit_code();
+        }
+    };
+    utils::display::reset_output();
+    utils::paths::set_mntn_dir(cli.mntn_dir.as_deref());
+    profiles::set_default_profile(None);
 
     match cli.command {
-        Some(Commands::Backup(args)) => backup::run(args),
-        Some(Commands::Restore(args)) => restore::run(args),
-        Some(Commands::Use(args)) => r#use::run(args),
-        Some(Commands::Profile(args)) => profile::run(args),
-        Some(Commands::Git(args)) => git::run(args),
-        Some(Commands::Sync(args)) => sync::run(args),
-        Some(Commands::Doctor(args)) => doctor::run(args),
-        Some(Commands::Secret { action }) => secret::run(action),
+        Some(mut command) => {
+            match Settings::load() {
+                Ok(settings) => settings.apply(&mut command),
+                Err(e) => {
+                    eprintln!("{}", red(format!("Error: {}", e).trim_end()));
+                    return 1;
+                }
+            }
+            if let Err(e) = command.check_requirements() {
+                let _ = e.print();
+                return e.exit_code();
+            }
+            let _lock = if commands::needs_lock(&command) {
+                match commands::lock::acquire() {
+                    Ok(lock) => Some(lock),
+                    Err(e) => {
+                        eprintln!("{}", red(&format!("Error: {:#}", e)));
+                        return 1;
+                    }
+                }
+            } else {
+                None
+            };
+            CommandExecutor::run(commands::dispatch(command).as_mut())
+        }
         None => {
             Cli::command().print_help().expect("Failed to print help");
+            0
         }
     }
 }
@@ -1,3 +1,3 @@
 fn main() {
-    mntn::run();
+    std::process::exit(mntn::run());
 }
@@ -1,13 +1,18 @@
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::sync::RwLock;
 
 use crate::utils::paths::get_encrypted_common_path;
 use crate::utils::paths::get_encrypted_profiles_path;
-use crate::utils::paths::{get_active_profile_path, get_common_path, get_profiles_path};
+use crate::utils::paths::{
+    get_active_profile_path, get_common_path, get_profiles_path, get_settings_path,
+};
 
+/// The profile whose layer backup and restore read from and write to, on
+/// top of the shared common layer.
 #[derive(Debug, Clone)]
-pub(crate) struct ActiveProfile {
+pub struct ActiveProfile {
     pub(crate) name: Option<String>,
 }
 
@@ -21,17 +26,23 @@ impl std::fmt::Display for ActiveProfile {
 }
 
 impl ActiveProfile {
-    pub(crate) fn with_profile(name: &str) -> Self {
+    /// Use the `name` profile layer, falling back to common for anything it
+    /// has no copy of. The name is not checked against `profiles.json`.
+    pub fn with_profile(name: &str) -> Self {
         Self {
             name: Some(name.to_string()),
         }
     }
 
-    pub(crate) fn common_only() -> Self {
+    /// Use only the common layer, as when no profile is active.
+    pub fn common_only() -> Self {
         Self { name: None }
     }
 
-    pub(crate) fn resolve(cli_profile: Option<&str>) -> Self {
+    /// The profile a command runs with: `cli_profile` if given, then
+    /// `MNTN_PROFILE`, then the profile saved by `mntn use`, then `profile`
+    /// in `config.toml`, and otherwise [`common_only`](Self::common_only).
+    pub fn resolve(cli_profile: Option<&str>) -> Self {
         if let Some(profile) = cli_profile {
             return Self::with_profile(profile);
         }
@@ -43,6 +54,11 @@ impl ActiveProfile {
         Self::common_only()
     }
 
+    /// The profile name, or `None` when only the common layer is used.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     pub(crate) fn get_backup_path(&self) -> PathBuf {
         match &self.name {
             Some(name) => get_profiles_path(name),
@@ -58,11 +74,43 @@ impl ActiveProfile {
     }
 }
 
+/// Where the active profile name was found, in the order they are checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProfileSource {
+    Environment,
+    File,
+    Settings,
+}
+
+impl std::fmt::Display for ProfileSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProfileSource::Environment => write!(f, "MNTN_PROFILE environment variable"),
+            ProfileSource::File => write!(f, "{}", get_active_profile_path().display()),
+            ProfileSource::Settings => write!(f, "profile in {}", get_settings_path().display()),
+        }
+    }
+}
+
+static DEFAULT_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Use `name` when nothing else selects a profile (the `profile` key in
+/// `config.toml`), or clear it with `None`. Each call replaces the last.
+pub(crate) fn set_default_profile(name: Option<&str>) {
+    let name = name.map(str::trim).filter(|name| !name.is_empty());
+    *DEFAULT_PROFILE.write().unwrap_or_else(|e| e.into_inner()) = name.map(str::to_string);
+}
+
 pub(crate) fn get_active_profile_name() -> Option<String> {
+    get_active_profile_with_source().map(|(name, _)| name)
+}
+
+/// The active profile name together with where it came from.
+pub(crate) fn get_active_profile_with_source() -> Option<(String, ProfileSource)> {
     if let Ok(profile) = std::env::var("MNTN_PROFILE") {
         let trimmed = profile.trim();
         if !trimmed.is_empty() {
-            return Some(trimmed.to_string());
+            return Some((trimmed.to_string(), ProfileSource::Environment));
         }
     }
 
@@ -70,11 +118,15 @@ pub(crate) fn get_active_profile_name() -> Option<String> {
     if let Ok(profile) = fs::read_to_string(&active_profile_path) {
         let trimmed = profile.trim();
         if !trimmed.is_empty() {
-            return Some(trimmed.to_string());
+            return Some((trimmed.to_string(), ProfileSource::File));
         }
     }
 
-    None
+    DEFAULT_PROFILE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .map(|name| (name, ProfileSource::Settings))
 }
 
 pub(crate) fn set_active_profile(profile_name: &str) -> io::Result<()> {
@@ -92,3 +144,42 @@ pub(crate) fn clear_active_profile() -> io::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profiles::SourceLayer;
+
+    #[test]
+    fn common_only_uses_the_common_layer() {
+        let profile = ActiveProfile::common_only();
+        assert_eq!(profile.name(), None);
+        assert_eq!(profile.get_backup_path(), get_common_path());
+        let layers: Vec<_> = profile
+            .get_candidate_sources(".zshrc")
+            .into_iter()
+            .map(|(_, layer)| layer)
+            .collect();
+        assert_eq!(layers, [SourceLayer::Common]);
+    }
+
+    #[test]
+    fn with_profile_resolves_to_the_profile_layer_first() {
+        let profile = ActiveProfile::with_profile("x");
+        assert_eq!(profile.name(), Some("x"));
+        assert_eq!(profile.get_backup_path(), get_profiles_path("x"));
+        assert_eq!(
+            profile.get_candidate_sources(".zshrc"),
+            [
+                (get_profiles_path("x").join(".zshrc"), SourceLayer::Profile),
+                (get_common_path().join(".zshrc"), SourceLayer::Common),
+            ]
+        );
+    }
+
+    #[test]
+    fn cli_profile_wins_over_everything_else() {
+        // Whatever MNTN_PROFILE, the active file, or config.toml say here.
+        assert_eq!(ActiveProfile::resolve(Some("x")).name(), Some("x"));
+    }
+}
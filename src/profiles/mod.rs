@@ -2,7 +2,11 @@ mod active;
 mod config;
 mod sources;
 
+pub use active::ActiveProfile;
 pub(crate) use active::{
-    ActiveProfile, clear_active_profile, get_active_profile_name, set_active_profile,
+    ProfileSource, clear_active_profile, get_active_profile_name, get_active_profile_with_source,
+    set_active_profile, set_default_profile,
 };
 pub(crate) use config::ProfileConfig;
+pub(crate) use sources::ResolvedSource;
+pub use sources::SourceLayer;
@@ -7,8 +7,9 @@ use crate::utils::paths::{
 
 use super::ActiveProfile;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum SourceLayer {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceLayer {
     Common,
     Profile,
 }
@@ -37,7 +38,7 @@ impl ActiveProfile {
         let candidates = self.get_candidate_sources(source_path);
 
         for (path, layer) in candidates {
-            if path.exists() {
+            if backup_exists(&path) {
                 return Some(ResolvedSource { path, layer });
             }
         }
@@ -45,6 +46,19 @@ impl ActiveProfile {
         None
     }
 
+    /// Like [`resolve_source`](Self::resolve_source), but only checks `layer`,
+    /// ignoring copies in any other layer.
+    pub(crate) fn resolve_source_in_layer(
+        &self,
+        source_path: &str,
+        layer: SourceLayer,
+    ) -> Option<ResolvedSource> {
+        self.get_candidate_sources(source_path)
+            .into_iter()
+            .find(|(path, candidate_layer)| *candidate_layer == layer && backup_exists(path))
+            .map(|(path, layer)| ResolvedSource { path, layer })
+    }
+
     pub(crate) fn get_candidate_sources(&self, source_path: &str) -> Vec<(PathBuf, SourceLayer)> {
         if !is_valid_source_path(source_path) {
             return Vec::new();
@@ -66,7 +80,7 @@ impl ActiveProfile {
     pub(crate) fn get_all_resolved_sources(&self, source_path: &str) -> Vec<ResolvedSource> {
         self.get_candidate_sources(source_path)
             .into_iter()
-            .filter(|(path, _)| path.exists())
+            .filter(|(path, _)| backup_exists(path))
             .map(|(path, layer)| ResolvedSource { path, layer })
             .collect()
     }
@@ -91,6 +105,19 @@ impl ActiveProfile {
         None
     }
 
+    /// Like [`resolve_encrypted_source`](Self::resolve_encrypted_source), but
+    /// only checks `layer`.
+    pub(crate) fn resolve_encrypted_source_in_layer(
+        &self,
+        source_path: &str,
+        layer: SourceLayer,
+    ) -> Option<ResolvedSource> {
+        self.get_candidate_encrypted_sources(source_path)
+            .into_iter()
+            .find(|(path, candidate_layer)| *candidate_layer == layer && path.exists())
+            .map(|(path, layer)| ResolvedSource { path, layer })
+    }
+
     pub(crate) fn get_candidate_encrypted_sources(
         &self,
         source_path: &str,
@@ -117,6 +144,12 @@ impl ActiveProfile {
     }
 }
 
+/// Whether a backup is present at `path`. A symlink counts even when its target
+/// is missing on this machine, since `preserve_symlink` backups store the link.
+fn backup_exists(path: &Path) -> bool {
+    path.exists() || path.is_symlink()
+}
+
 fn is_valid_source_path(source_path: &str) -> bool {
     if source_path.is_empty() {
         return false;
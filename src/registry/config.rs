@@ -2,21 +2,79 @@ use directories_next::BaseDirs;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
 
-use crate::registry::{Registry, RegistryEntryLike};
+use crate::registry::{REGISTRY_VERSION, Registry, RegistryEntryLike};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(crate) struct ConfigRegistryEntry {
     pub name: String,
     pub description: Option<String>,
     pub enabled: bool,
     pub source_path: String,
     pub target_path: PathBuf,
+    /// Opt out of the doctor warning for targets outside the home directory.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_system: bool,
+    /// Unix permissions applied to the target after restore, written as an
+    /// octal string such as `"600"`.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "octal_mode")]
+    pub mode: Option<u32>,
+    /// Back up a symlinked target as the link itself and recreate the link on
+    /// restore, instead of copying the file it points to.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preserve_symlink: bool,
+    /// Treat `target_path` as a glob pattern (`*`, `?`, `[...]`, `**`). Each
+    /// matching file is handled on its own and kept under `source_path` at
+    /// its path relative to the pattern's fixed leading directories.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub glob: bool,
+    /// Only ever use a profile-layer copy: restore skips the entry instead of
+    /// falling back to the common layer, and backup skips it when no profile
+    /// is active.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub profile_required: bool,
+    /// Shell command run after the entry is restored, such as
+    /// `tmux source-file ~/.tmux.conf`. Failures are reported, not fatal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_restore: Option<String>,
+    /// Shell command run before the entry is backed up, to generate what it
+    /// captures (an exported extension list, a settings dump). Failures are
+    /// reported, and the entry is still backed up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_backup: Option<String>,
+}
+
+/// (De)serialize file modes as octal strings, since `"600"` is what users
+/// write and what `chmod` takes, while the JSON number would be `384`.
+mod octal_mode {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(mode: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error> {
+        match mode {
+            Some(mode) => serializer.serialize_str(&format!("{:o}", mode)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u32>, D::Error> {
+        let Some(value) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        match u32::from_str_radix(value.trim_start_matches("0o"), 8) {
+            Ok(mode) if mode <= 0o7777 => Ok(Some(mode)),
+            _ => Err(D::Error::custom(format!(
+                "invalid mode '{}' (expected octal such as \"600\")",
+                value
+            ))),
+        }
+    }
 }
 
 use crate::impl_registry_entry_like;
 use crate::utils::paths::{get_ghostty_config_path, get_xdg_or_default_config_path};
 
-impl_registry_entry_like!(ConfigRegistryEntry);
+impl_registry_entry_like!(ConfigRegistryEntry, target_path);
 
 pub(crate) type ConfigRegistry = Registry<ConfigRegistryEntry>;
 
@@ -27,7 +85,9 @@ impl Default for ConfigRegistry {
         let base_dirs = BaseDirs::new()
             .expect("failed to get user base dirs: $HOME not set or platform dirs unavailable");
         let home_dir = base_dirs.home_dir();
-        let data_dir = base_dirs.data_dir();
+        // `~/.config` on Linux (or `$XDG_CONFIG_HOME`), `~/Library/Application
+        // Support` on macOS, and the roaming AppData folder on Windows.
+        let config_dir = base_dirs.config_dir();
 
         entries.insert(
             "bashrc".to_string(),
@@ -37,6 +97,7 @@ impl Default for ConfigRegistry {
                 target_path: home_dir.join(".bashrc"),
                 enabled: true,
                 description: Some("Bash shell configuration file".to_string()),
+                ..Default::default()
             },
         );
 
@@ -48,6 +109,7 @@ impl Default for ConfigRegistry {
                 target_path: home_dir.join(".zshrc"),
                 enabled: true,
                 description: Some("Zsh shell configuration file".to_string()),
+                ..Default::default()
             },
         );
 
@@ -59,6 +121,7 @@ impl Default for ConfigRegistry {
                 target_path: home_dir.join(".vimrc"),
                 enabled: true,
                 description: Some("Vim editor configuration".to_string()),
+                ..Default::default()
             },
         );
 
@@ -67,9 +130,10 @@ impl Default for ConfigRegistry {
             ConfigRegistryEntry {
                 name: "VSCode Settings".to_string(),
                 source_path: "vscode/settings.json".to_string(),
-                target_path: data_dir.join("Code/User/settings.json"),
+                target_path: config_dir.join("Code/User/settings.json"),
                 enabled: true,
                 description: Some("Visual Studio Code user settings".to_string()),
+                ..Default::default()
             },
         );
 
@@ -78,9 +142,10 @@ impl Default for ConfigRegistry {
             ConfigRegistryEntry {
                 name: "VSCode Keybindings".to_string(),
                 source_path: "vscode/keybindings.json".to_string(),
-                target_path: data_dir.join("Code/User/keybindings.json"),
+                target_path: config_dir.join("Code/User/keybindings.json"),
                 enabled: true,
                 description: Some("Visual Studio Code keybindings".to_string()),
+                ..Default::default()
             },
         );
 
@@ -92,6 +157,7 @@ impl Default for ConfigRegistry {
                 target_path: get_xdg_or_default_config_path("zed/settings.json"),
                 enabled: true,
                 description: Some("Zed user settings".to_string()),
+                ..Default::default()
             },
         );
 
@@ -103,6 +169,7 @@ impl Default for ConfigRegistry {
                 target_path: get_ghostty_config_path(),
                 enabled: true,
                 description: Some("Ghostty terminal emulator configuration".to_string()),
+                ..Default::default()
             },
         );
 
@@ -114,12 +181,14 @@ impl Default for ConfigRegistry {
                 target_path: home_dir.join(".gitconfig"),
                 enabled: true,
                 description: Some("Global Git configuration".to_string()),
+                ..Default::default()
             },
         );
 
         Self {
-            version: "1.0.0".to_string(),
+            version: REGISTRY_VERSION.to_string(),
             entries,
+            invalid_entries: Default::default(),
         }
     }
 }
@@ -2,20 +2,23 @@ use directories_next::BaseDirs;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::PathBuf};
 
-use crate::registry::{Registry, RegistryEntryLike};
+use crate::registry::{REGISTRY_VERSION, Registry, RegistryEntryLike};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(crate) struct EncryptedRegistryEntry {
     pub name: String,
     pub description: Option<String>,
     pub enabled: bool,
     pub source_path: String,
     pub target_path: PathBuf,
+    /// Opt out of the doctor warning for targets outside the home directory.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_system: bool,
 }
 
 use crate::impl_registry_entry_like;
 
-impl_registry_entry_like!(EncryptedRegistryEntry);
+impl_registry_entry_like!(EncryptedRegistryEntry, target_path);
 
 pub(crate) type EncryptedRegistry = Registry<EncryptedRegistryEntry>;
 
@@ -34,12 +37,14 @@ impl Default for EncryptedRegistry {
                 target_path: home_dir.join(".ssh/config"),
                 enabled: true,
                 description: Some("SSH client configuration file".to_string()),
+                ..Default::default()
             },
         );
 
         Self {
-            version: "1.0.0".to_string(),
+            version: REGISTRY_VERSION.to_string(),
             entries,
+            invalid_entries: Default::default(),
         }
     }
 }
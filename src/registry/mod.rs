@@ -2,12 +2,35 @@ pub mod config;
 pub mod encrypted;
 pub mod package;
 
-use crate::errors::Result;
+use crate::errors::{AppError, Result};
+use crate::utils::display::{warning, yellow};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, collections::HashMap, path::PathBuf};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Registry schema version written by this release. Registries with an older
+/// version are migrated forward when loaded.
+///
+/// - 1.1.0: optional entry fields (`mode`, `glob`, `preserve_symlink`,
+///   `profile_required`, `post_restore`, `pre_backup`, `env`, `format`,
+///   `normalize`), and target paths inside the home directory stored as
+///   `~/...` so registries work across machines.
+pub(crate) const REGISTRY_VERSION: &str = "1.1.0";
 
 pub(crate) trait RegistryEntryLike {
     fn is_enabled(&self) -> bool;
+
+    /// Upgrade an entry loaded from a registry written with `_from_version`.
+    /// New optional fields are filled in by serde defaults, so most schema
+    /// changes need nothing here.
+    fn migrate(&mut self, _from_version: &str) {}
+
+    /// Expand `~` and environment variables in any paths the entry holds.
+    /// Runs on every load and is never saved, so registries stay portable.
+    fn expand_paths(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[macro_export]
@@ -19,12 +42,39 @@ macro_rules! impl_registry_entry_like {
             }
         }
     };
+    ($t:ty, $($path:ident),+) => {
+        impl RegistryEntryLike for $t {
+            fn is_enabled(&self) -> bool {
+                self.enabled
+            }
+
+            /// Before 1.1.0, target paths were stored expanded, tying the
+            /// registry to one home directory.
+            fn migrate(&mut self, from_version: &str) {
+                if $crate::registry::parse_version(from_version) < (1, 1, 0) {
+                    $(self.$path = $crate::utils::paths::collapse_home(&self.$path);)+
+                }
+            }
+
+            fn expand_paths(&mut self) -> $crate::errors::Result<()> {
+                $(self.$path = $crate::utils::paths::expand_path(&self.$path)?;)+
+                Ok(())
+            }
+        }
+    };
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct Registry<T> {
+    /// Missing in registries older than versioning, which parse as the
+    /// oldest schema.
+    #[serde(default)]
     pub version: String,
     pub entries: HashMap<String, T>,
+    /// Entries left out of `entries` because their paths could not be
+    /// expanded on this machine, with the reason. Reported by `doctor`.
+    #[serde(skip)]
+    pub invalid_entries: BTreeMap<String, String>,
 }
 
 impl<T> Registry<T>
@@ -36,8 +86,14 @@ where
         Self: Default,
     {
         if path.exists() {
-            let content = std::fs::read_to_string(path)?;
-            let registry: Registry<T> = serde_json::from_str(&content)?;
+            let mut registry = Self::read(path).map_err(|error| AppError::RegistryLoad {
+                path: path.clone(),
+                error: Box::new(error),
+            })?;
+            if registry.migrate_schema()? {
+                registry.save(path)?;
+            }
+            registry.expand_entry_paths(path);
             Ok(registry)
         } else {
             let registry = Self::default();
@@ -46,6 +102,58 @@ where
         }
     }
 
+    /// Expand every entry's paths, moving entries that cannot be expanded
+    /// (such as `~user` for a user this machine does not have) into
+    /// `invalid_entries` with a warning, so one bad entry does not stop the
+    /// rest of the registry from loading.
+    fn expand_entry_paths(&mut self, path: &Path) {
+        let mut invalid = Vec::new();
+        for (id, entry) in self.entries.iter_mut() {
+            if let Err(error) = entry.expand_paths() {
+                invalid.push((id.clone(), error.to_string()));
+            }
+        }
+        invalid.sort();
+        for (id, reason) in invalid {
+            warning!(
+                "{}",
+                yellow(&format!(
+                    "   skipped {} in {}: {}",
+                    id,
+                    path.display(),
+                    reason
+                ))
+            );
+            self.entries.remove(&id);
+            self.invalid_entries.insert(id, reason);
+        }
+    }
+
+    fn read(path: &PathBuf) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Bring a registry up to [`REGISTRY_VERSION`], returning whether anything
+    /// changed. Registries written by a newer release are rejected rather than
+    /// silently losing fields this version does not know about.
+    pub(crate) fn migrate_schema(&mut self) -> Result<bool> {
+        match parse_version(&self.version).cmp(&parse_version(REGISTRY_VERSION)) {
+            Ordering::Equal => Ok(false),
+            Ordering::Greater => Err(AppError::UnsupportedRegistryVersion {
+                found: self.version.clone(),
+                supported: REGISTRY_VERSION.to_string(),
+            }),
+            Ordering::Less => {
+                let from_version = std::mem::replace(&mut self.version, REGISTRY_VERSION.into());
+                for entry in self.entries.values_mut() {
+                    entry.migrate(&from_version);
+                }
+                Ok(true)
+            }
+        }
+    }
+
     pub(crate) fn save(&self, path: &PathBuf) -> Result<()> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -62,7 +170,197 @@ where
         Ok(())
     }
 
+    /// Enabled entries sorted by id, so output is stable from run to run.
     pub(crate) fn get_enabled_entries(&self) -> impl Iterator<Item = (&String, &T)> {
-        self.entries.iter().filter(|(_, e)| e.is_enabled())
+        self.get_entries(false)
+    }
+
+    /// Entries sorted by id, including disabled ones when `include_disabled` is set.
+    pub(crate) fn get_entries(
+        &self,
+        include_disabled: bool,
+    ) -> impl Iterator<Item = (&String, &T)> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|(_, e)| include_disabled || e.is_enabled())
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries.into_iter()
+    }
+
+    /// Entries sorted by id that `filter` selects.
+    pub(crate) fn filtered_entries<'a>(
+        &'a self,
+        filter: &'a EntryFilter,
+    ) -> impl Iterator<Item = (&'a String, &'a T)> + 'a {
+        self.get_entries(filter.include_disabled)
+            .filter(move |(id, _)| {
+                !filter.excluded.contains(id)
+                    && (filter.only.is_empty() || filter.only.contains(id))
+            })
+    }
+}
+
+/// Which registry entries a command processes, so backup and restore select
+/// them the same way.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EntryFilter {
+    /// Also process entries whose `enabled` flag is off.
+    pub include_disabled: bool,
+    /// Ids to leave out for this run (`--exclude-entry`).
+    pub excluded: Vec<String>,
+    /// When not empty, only these ids are processed.
+    pub only: Vec<String>,
+}
+
+/// Parse a `major.minor.patch` version; missing or malformed parts count as 0
+/// so unversioned registries are treated as the oldest schema.
+pub(crate) fn parse_version(version: &str) -> (u32, u32, u32) {
+    let mut parts = version
+        .trim()
+        .split('.')
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct TestEntry {
+        enabled: bool,
+    }
+
+    crate::impl_registry_entry_like!(TestEntry);
+
+    fn registry(count: usize) -> Registry<TestEntry> {
+        let entries = (0..count)
+            .map(|i| {
+                (
+                    format!("entry-{:03}", (i * 37) % count),
+                    TestEntry {
+                        enabled: i % 5 != 0,
+                    },
+                )
+            })
+            .collect();
+        Registry {
+            version: REGISTRY_VERSION.to_string(),
+            entries,
+            invalid_entries: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn enabled_entries_are_listed_in_the_same_sorted_order_every_time() {
+        let registry = registry(200);
+        let first: Vec<&String> = registry.get_enabled_entries().map(|(id, _)| id).collect();
+        let second: Vec<&String> = registry.get_enabled_entries().map(|(id, _)| id).collect();
+
+        assert_eq!(first, second);
+        assert!(first.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(first.len(), 160);
+    }
+
+    #[test]
+    fn filtered_entries_keep_id_order() {
+        let registry = registry(50);
+        let filter = EntryFilter {
+            include_disabled: true,
+            excluded: vec!["entry-001".to_string()],
+            only: Vec::new(),
+        };
+        let ids: Vec<&String> = registry
+            .filtered_entries(&filter)
+            .map(|(id, _)| id)
+            .collect();
+
+        assert_eq!(ids.len(), 49);
+        assert!(!ids.iter().any(|id| *id == "entry-001"));
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    fn write_fixture(name: &str, version_field: &str, target: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "mntn-registry-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let fixture = format!(
+            r#"{{{}"entries": {{"zshrc": {{"name": "Zsh", "description": null,
+                "enabled": true, "source_path": ".zshrc", "target_path": "{}"}}}}}}"#,
+            version_field, target
+        );
+        std::fs::write(&path, fixture).unwrap();
+        path
+    }
+
+    #[test]
+    fn version_1_0_0_registry_is_upgraded_and_saved() {
+        let home = directories_next::BaseDirs::new()
+            .unwrap()
+            .home_dir()
+            .to_path_buf();
+        let target = home.join(".zshrc");
+        let path = write_fixture(
+            "upgrade",
+            r#""version": "1.0.0", "#,
+            &target.to_string_lossy(),
+        );
+
+        let registry = config::ConfigRegistry::load_or_create(&path).unwrap();
+        assert_eq!(registry.version, REGISTRY_VERSION);
+        assert_eq!(registry.entries["zshrc"].target_path, target);
+
+        let saved: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], REGISTRY_VERSION);
+        assert_eq!(saved["entries"]["zshrc"]["target_path"], "~/.zshrc");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unversioned_registry_is_treated_as_the_oldest_schema() {
+        let path = write_fixture("unversioned", "", "/etc/hosts");
+        let registry = config::ConfigRegistry::load_or_create(&path).unwrap();
+        assert_eq!(registry.version, REGISTRY_VERSION);
+        assert_eq!(
+            registry.entries["zshrc"].target_path,
+            PathBuf::from("/etc/hosts")
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn entry_with_unknown_user_is_skipped_not_fatal() {
+        let path = write_fixture(
+            "unknown-user",
+            r#""version": "1.1.0", "#,
+            "~mntn-no-such-user/.zshrc",
+        );
+        let warnings = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = warnings.clone();
+        let registry = crate::utils::display::with_progress(
+            move |progress| sink.borrow_mut().push(progress.message.clone()),
+            || config::ConfigRegistry::load_or_create(&path).unwrap(),
+        );
+
+        assert!(registry.entries.is_empty());
+        assert!(registry.invalid_entries["zshrc"].contains("~mntn-no-such-user"));
+        assert!(warnings.borrow().iter().any(|line| line.contains("zshrc")));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn newer_registry_is_rejected() {
+        let path = write_fixture("newer", r#""version": "99.0.0", "#, "~/.zshrc");
+        assert!(config::ConfigRegistry::load_or_create(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::registry::{Registry, RegistryEntryLike};
+use crate::registry::{EntryFilter, REGISTRY_VERSION, Registry, RegistryEntryLike};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub(crate) struct PackageRegistryEntry {
     pub name: String,
     pub description: Option<String>,
@@ -12,6 +12,39 @@ pub(crate) struct PackageRegistryEntry {
     pub args: Vec<String>,
     pub output_file: String,
     pub platforms: Option<Vec<String>>,
+    /// Extra environment variables set for `command`, e.g. `HOMEBREW_NO_AUTO_UPDATE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<HashMap<String, String>>,
+    /// How `output_file` is written. Defaults to the raw command output.
+    #[serde(default, skip_serializing_if = "PackageOutputFormat::is_text")]
+    pub format: PackageOutputFormat,
+    /// Sort `text` output and drop blank and duplicate lines before writing.
+    /// On unless set to `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize: Option<bool>,
+}
+
+impl PackageRegistryEntry {
+    pub fn normalizes(&self) -> bool {
+        self.normalize.unwrap_or(true)
+    }
+}
+
+/// `text` keeps the command output as printed. `json` parses the output of
+/// known package managers into a sorted list of names and versions, so the
+/// file only changes when the installed packages do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PackageOutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl PackageOutputFormat {
+    fn is_text(&self) -> bool {
+        *self == PackageOutputFormat::Text
+    }
 }
 
 use crate::impl_registry_entry_like;
@@ -34,6 +67,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("Homebrew installed packages".to_string()),
                 platforms: Some(vec!["macos".to_string(), "linux".to_string()]),
+                ..Default::default()
             },
         );
 
@@ -47,6 +81,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("Homebrew installed casks (applications)".to_string()),
                 platforms: Some(vec!["macos".to_string()]),
+                ..Default::default()
             },
         );
 
@@ -60,6 +95,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("npm globally installed packages".to_string()),
                 platforms: None,
+                ..Default::default()
             },
         );
 
@@ -73,6 +109,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("pnpm globally installed packages".to_string()),
                 platforms: None,
+                ..Default::default()
             },
         );
 
@@ -86,6 +123,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("Bun globally installed packages".to_string()),
                 platforms: None,
+                ..Default::default()
             },
         );
 
@@ -103,6 +141,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("Deno globally installed packages".to_string()),
                 platforms: None,
+                ..Default::default()
             },
         );
 
@@ -116,6 +155,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("Cargo installed packages".to_string()),
                 platforms: None,
+                ..Default::default()
             },
         );
 
@@ -129,6 +169,7 @@ impl Default for PackageRegistry {
                 enabled: true,
                 description: Some("uv installed tools".to_string()),
                 platforms: None,
+                ..Default::default()
             },
         );
 
@@ -142,12 +183,14 @@ impl Default for PackageRegistry {
                 enabled: false,
                 description: Some("pip globally installed packages".to_string()),
                 platforms: None,
+                ..Default::default()
             },
         );
 
         Self {
-            version: "1.0.0".to_string(),
+            version: REGISTRY_VERSION.to_string(),
             entries,
+            invalid_entries: Default::default(),
         }
     }
 }
@@ -156,14 +199,13 @@ impl PackageRegistry {
     pub fn get_platform_compatible_entries<'a>(
         &'a self,
         current_platform: &'a str,
+        filter: &'a EntryFilter,
     ) -> impl Iterator<Item = (&'a String, &'a PackageRegistryEntry)> + 'a {
-        self.entries.iter().filter(move |(_, entry)| {
-            entry.enabled
-                && match &entry.platforms {
-                    Some(platforms) => platforms.contains(&current_platform.to_string()),
-                    None => true,
-                }
-        })
+        self.filtered_entries(filter)
+            .filter(move |(_, entry)| match &entry.platforms {
+                Some(platforms) => platforms.contains(&current_platform.to_string()),
+                None => true,
+            })
     }
 
     pub fn get_current_platform() -> String {
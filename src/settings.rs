@@ -0,0 +1,244 @@
+use serde::Deserialize;
+use std::fs;
+use std::num::NonZeroU32;
+
+use crate::cli::{Commands, SyncStrategy};
+use crate::errors::{AppError, Result};
+use crate::profiles::{SourceLayer, set_default_profile};
+use crate::utils::paths::get_settings_path;
+
+/// Command defaults read from `~/.mntn/config.toml`.
+///
+/// Precedence is CLI flag, then this file, then the built-in default. A
+/// boolean set to `true` here is turned off for one run with the flag's
+/// `--no-` form, e.g. `--no-snapshot`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Settings {
+    /// Profile used when neither `--profile`, `MNTN_PROFILE`, nor `mntn use`
+    /// picks one.
+    pub profile: Option<String>,
+    pub backup: BackupDefaults,
+    pub restore: RestoreDefaults,
+    pub sync: SyncDefaults,
+    pub doctor: DoctorDefaults,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct BackupDefaults {
+    pub skip_encrypted: bool,
+    pub snapshot: bool,
+    pub keep_snapshots: Option<NonZeroU32>,
+    pub follow_symlinks: bool,
+    pub jobs: Option<NonZeroU32>,
+    pub ignore_missing_commands: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct RestoreDefaults {
+    pub skip_encrypted: bool,
+    pub backup_first: bool,
+    pub layer: Option<SourceLayer>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct SyncDefaults {
+    pub pull: bool,
+    pub message_template: Option<String>,
+    pub strategy: Option<SyncStrategy>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct DoctorDefaults {
+    pub skip_encrypted: bool,
+    pub parallel: bool,
+}
+
+impl Settings {
+    /// Load the settings file, falling back to built-in defaults when it does
+    /// not exist.
+    pub(crate) fn load() -> Result<Self> {
+        let path = get_settings_path();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(AppError::SettingsLoad {
+                    path,
+                    error: Box::new(e.into()),
+                });
+            }
+        };
+        toml::from_str(&content).map_err(|e| AppError::SettingsLoad {
+            path,
+            error: Box::new(e.into()),
+        })
+    }
+
+    /// Fill in every option the command line left unset.
+    pub(crate) fn apply(&self, command: &mut Commands) {
+        set_default_profile(self.profile.as_deref());
+
+        match command {
+            Commands::Backup(args) => {
+                let defaults = &self.backup;
+                apply_flag(
+                    &mut args.skip_encrypted,
+                    args.no_skip_encrypted,
+                    defaults.skip_encrypted,
+                );
+                apply_flag(
+                    &mut args.follow_symlinks,
+                    args.no_follow_symlinks,
+                    defaults.follow_symlinks,
+                );
+                // --output-dir exports and dry runs are never snapshotted.
+                if args.output_dir.is_none() && !args.dry_run {
+                    apply_flag(&mut args.snapshot, args.no_snapshot, defaults.snapshot);
+                }
+                if args.snapshot && args.keep_snapshots.is_none() {
+                    args.keep_snapshots = defaults.keep_snapshots.map(NonZeroU32::get);
+                }
+                apply_flag(
+                    &mut args.ignore_missing_commands,
+                    args.no_ignore_missing_commands,
+                    defaults.ignore_missing_commands,
+                );
+                if args.jobs.is_none() {
+                    args.jobs = defaults.jobs.map(NonZeroU32::get);
+                }
+            }
+            Commands::Restore(args) => {
+                let defaults = &self.restore;
+                apply_flag(
+                    &mut args.skip_encrypted,
+                    args.no_skip_encrypted,
+                    defaults.skip_encrypted,
+                );
+                apply_flag(
+                    &mut args.backup_first,
+                    args.no_backup_first,
+                    defaults.backup_first,
+                );
+                if args.layer.is_none() {
+                    args.layer = defaults.layer;
+                }
+            }
+            Commands::Sync(args) => {
+                let defaults = &self.sync;
+                apply_flag(&mut args.pull, args.no_pull, defaults.pull);
+                if args.message.is_none() && args.message_template.is_none() {
+                    args.message_template = defaults.message_template.clone();
+                }
+                if args.pull && args.strategy.is_none() {
+                    args.strategy = defaults.strategy;
+                }
+            }
+            Commands::Doctor(args) => {
+                let defaults = &self.doctor;
+                apply_flag(
+                    &mut args.skip_encrypted,
+                    args.no_skip_encrypted,
+                    defaults.skip_encrypted,
+                );
+                apply_flag(&mut args.parallel, args.no_parallel, defaults.parallel);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Turn `flag` on from the settings unless the command line already set it
+/// or passed its `--no-` form.
+fn apply_flag(flag: &mut bool, negated: bool, default: bool) {
+    *flag = *flag || (default && !negated);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    fn command(settings: &str, args: &[&str]) -> Commands {
+        let settings: Settings = toml::from_str(settings).unwrap();
+        let mut command = Cli::try_parse_from(std::iter::once(&"mntn").chain(args))
+            .unwrap()
+            .command
+            .unwrap();
+        settings.apply(&mut command);
+        command
+    }
+
+    fn backup_args(settings: &str, args: &[&str]) -> crate::cli::BackupArgs {
+        let args: Vec<&str> = std::iter::once("backup")
+            .chain(args.iter().copied())
+            .collect();
+        match command(settings, &args) {
+            Commands::Backup(args) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn settings_fill_in_unset_flags() {
+        let args = backup_args("[backup]\nsnapshot = true\n", &[]);
+        assert!(args.snapshot);
+        assert!(!args.skip_encrypted);
+    }
+
+    #[test]
+    fn negated_flag_overrides_settings() {
+        let args = backup_args("[backup]\nsnapshot = true\n", &["--no-snapshot"]);
+        assert!(!args.snapshot);
+    }
+
+    #[test]
+    fn last_of_a_flag_and_its_negation_wins() {
+        assert!(backup_args("", &["--no-snapshot", "--snapshot"]).snapshot);
+        assert!(!backup_args("", &["--snapshot", "--no-snapshot"]).snapshot);
+    }
+
+    #[test]
+    fn settings_can_supply_the_flag_another_depends_on() {
+        let backup = command(
+            "[backup]\nsnapshot = true\n",
+            &["backup", "--keep-snapshots", "3"],
+        );
+        assert!(backup.check_requirements().is_ok());
+        let sync = command("[sync]\npull = true\n", &["sync", "--strategy", "ours"]);
+        assert!(sync.check_requirements().is_ok());
+
+        assert!(
+            command("", &["backup", "--keep-snapshots", "3"])
+                .check_requirements()
+                .is_err()
+        );
+        assert!(
+            command("", &["sync", "--strategy", "ours"])
+                .check_requirements()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn settings_never_snapshot_exports_or_dry_runs() {
+        let settings = "[backup]\nsnapshot = true\nkeep_snapshots = 3\n";
+        for args in [["--output-dir", "/tmp/export"].as_slice(), &["--dry-run"]] {
+            let args = backup_args(settings, args);
+            assert!(!args.snapshot);
+            assert_eq!(args.keep_snapshots, None);
+        }
+    }
+
+    #[test]
+    fn default_profile_is_read_from_the_top_level() {
+        let settings: Settings = toml::from_str("profile = \"work\"\n").unwrap();
+        assert_eq!(settings.profile.as_deref(), Some("work"));
+        assert!(toml::from_str::<Settings>("[backup]\nprofile = \"work\"\n").is_err());
+    }
+}
@@ -1,4 +1,7 @@
+use std::cell::RefCell;
 use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub(crate) fn short_component(path: &Path) -> String {
     path.file_name()
@@ -26,3 +29,171 @@ pub(crate) fn yellow(text: &str) -> String {
 pub(crate) fn red(text: &str) -> String {
     color(text, COLOR_RED)
 }
+
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Send progress output to stderr for the rest of the run, keeping stdout free
+/// for machine-readable output such as `backup --print-manifest`.
+pub(crate) fn send_status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Go back to printing progress on stdout with every entry line, undoing
+/// [`send_status_to_stderr`] and [`hide_entry_lines`] from an earlier run.
+pub(crate) fn reset_output() {
+    STATUS_TO_STDERR.store(false, Ordering::Relaxed);
+    ENTRY_LINES_HIDDEN.store(false, Ordering::Relaxed);
+}
+
+pub(crate) fn status_to_stderr() -> bool {
+    STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// `println!` for progress output; goes to stderr after [`send_status_to_stderr`].
+macro_rules! status {
+    ($($arg:tt)*) => {
+        $crate::utils::display::emit(
+            $crate::utils::display::ProgressKind::Status,
+            format!($($arg)*),
+        )
+    };
+}
+
+pub(crate) use status;
+
+static ENTRY_LINES_HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Drop per-entry progress lines for the rest of the run, leaving section
+/// headers, counts, warnings and errors, as with `backup --summary-only`.
+pub(crate) fn hide_entry_lines() {
+    ENTRY_LINES_HIDDEN.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn entry_lines_hidden() -> bool {
+    ENTRY_LINES_HIDDEN.load(Ordering::Relaxed)
+}
+
+/// [`status!`] for a line about a single entry that went as expected; dropped
+/// after [`hide_entry_lines`]. Skips and failures should use [`warning!`].
+macro_rules! entry_status {
+    ($($arg:tt)*) => {
+        $crate::utils::display::emit(
+            $crate::utils::display::ProgressKind::Entry,
+            format!($($arg)*),
+        )
+    };
+}
+
+pub(crate) use entry_status;
+
+/// `eprintln!` for skips, failures and other warnings in progress output.
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::utils::display::emit(
+            $crate::utils::display::ProgressKind::Warning,
+            format!($($arg)*),
+        )
+    };
+}
+
+pub(crate) use warning;
+
+/// What a [`Progress`] line reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressKind {
+    /// Section headers, settings and counts.
+    Status,
+    /// A single entry that went as expected.
+    Entry,
+    /// A skipped entry, a failure, or something else worth a look.
+    Warning,
+}
+
+/// One line of progress from [`backup`](crate::backup) or
+/// [`restore`](crate::restore). `message` is the line as `mntn` prints it,
+/// colours included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub kind: ProgressKind,
+    pub message: String,
+}
+
+type ProgressSink = Rc<dyn Fn(&Progress)>;
+
+thread_local! {
+    static PROGRESS_SINK: RefCell<Option<ProgressSink>> = const { RefCell::new(None) };
+}
+
+/// Puts the previous progress sink back when a run ends, even on a panic.
+struct SinkGuard(Option<ProgressSink>);
+
+impl Drop for SinkGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        PROGRESS_SINK.with(|sink| *sink.borrow_mut() = previous);
+    }
+}
+
+/// Run `f` with every progress line on this thread handed to `on_progress`
+/// instead of being printed.
+pub(crate) fn with_progress<R>(
+    on_progress: impl Fn(&Progress) + 'static,
+    f: impl FnOnce() -> R,
+) -> R {
+    let previous = PROGRESS_SINK.with(|sink| sink.borrow_mut().replace(Rc::new(on_progress)));
+    let _guard = SinkGuard(previous);
+    f()
+}
+
+/// Hand a progress line to the sink set by [`with_progress`], or print it.
+pub(crate) fn emit(kind: ProgressKind, message: String) {
+    let progress = Progress { kind, message };
+    match PROGRESS_SINK.with(|sink| sink.borrow().clone()) {
+        Some(sink) => sink(&progress),
+        None => print_progress(&progress),
+    }
+}
+
+/// Print a progress line the way the CLI does: warnings on stderr, entry
+/// lines only until [`hide_entry_lines`], and the rest on stdout until
+/// [`send_status_to_stderr`].
+pub(crate) fn print_progress(progress: &Progress) {
+    match progress.kind {
+        ProgressKind::Warning => eprintln!("{}", progress.message),
+        ProgressKind::Entry if entry_lines_hidden() => {}
+        _ if status_to_stderr() => eprintln!("{}", progress.message),
+        _ => println!("{}", progress.message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn progress_goes_to_the_sink_while_it_is_set() {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&lines);
+        with_progress(
+            move |progress| sink.lock().unwrap().push(progress.clone()),
+            || {
+                status!("   Target: {}", "common");
+                entry_status!("     {}", "a.conf");
+                warning!("     skipped {}", "b.conf");
+            },
+        );
+
+        let kinds: Vec<_> = lines.lock().unwrap().iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ProgressKind::Status,
+                ProgressKind::Entry,
+                ProgressKind::Warning
+            ]
+        );
+        assert_eq!(lines.lock().unwrap()[0].message, "   Target: common");
+        assert!(PROGRESS_SINK.with(|sink| sink.borrow().is_none()));
+    }
+}
@@ -1,20 +1,307 @@
-use std::{fs, io, path::Path};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{fs, io, thread};
 
-pub(crate) fn copy_dir_recursive(src: &Path, dst: &Path) -> io::Result<()> {
+/// Copy a directory tree. Symlinks are skipped unless `follow_symlinks` is set,
+/// in which case their targets are copied as real files and directories.
+pub(crate) fn copy_dir_recursive(src: &Path, dst: &Path, follow_symlinks: bool) -> io::Result<()> {
+    let mut visited = HashSet::new();
+    mark_dir_visited(&mut visited, src);
+    copy_dir_inner(src, dst, follow_symlinks, &mut visited)
+}
+
+fn copy_dir_inner(
+    src: &Path,
+    dst: &Path,
+    follow_symlinks: bool,
+    visited: &mut HashSet<PathBuf>,
+) -> io::Result<()> {
     for entry in fs::read_dir(src)? {
         let entry = entry?;
         let src_path = entry.path();
         let dst_path = dst.join(entry.file_name());
 
-        let metadata = fs::symlink_metadata(&src_path)?;
-        if metadata.file_type().is_symlink() {
+        let Some(metadata) = entry_metadata(&src_path, follow_symlinks)? else {
             continue;
-        } else if metadata.is_dir() {
+        };
+        if metadata.is_dir() {
+            if !mark_dir_visited(visited, &src_path) {
+                continue;
+            }
             fs::create_dir_all(&dst_path)?;
-            copy_dir_recursive(&src_path, &dst_path)?;
+            copy_dir_inner(&src_path, &dst_path, follow_symlinks, visited)?;
         } else if metadata.is_file() {
             fs::copy(&src_path, &dst_path)?;
         }
     }
     Ok(())
 }
+
+/// Copy the tree under `src` into `dst`, recreating symlinks as symlinks
+/// instead of following or skipping them, so the copy matches the original.
+pub(crate) fn copy_dir_keeping_symlinks(src: &Path, dst: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+
+        if file_type.is_symlink() {
+            replace_with_symlink(&fs::read_link(&src_path)?, &dst_path)?;
+        } else if file_type.is_dir() {
+            fs::create_dir_all(&dst_path)?;
+            copy_dir_keeping_symlinks(&src_path, &dst_path)?;
+        } else if file_type.is_file() {
+            fs::copy(&src_path, &dst_path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Total size of the files under `path`, splitting the top-level entries
+/// across worker threads. Worth it for large trees such as the snapshot store.
+/// The threads share one set of visited directories, so a directory reached
+/// through a followed symlink is counted once, as in a sequential walk.
+pub(crate) fn calculate_dir_size_parallel(path: &Path, follow_symlinks: bool) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    let paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    if paths.is_empty() {
+        return 0;
+    }
+
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(paths.len());
+    let chunk_size = paths.len().div_ceil(workers);
+    let visited = Mutex::new(HashSet::new());
+    mark_dir_visited(&mut visited.lock().expect("visited set poisoned"), path);
+
+    thread::scope(|s| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let visited = &visited;
+                s.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|p| entry_size(p, follow_symlinks, visited))
+                        .sum::<u64>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("directory size thread panicked"))
+            .sum()
+    })
+}
+
+fn dir_size(path: &Path, follow_symlinks: bool, visited: &Mutex<HashSet<PathBuf>>) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| entry_size(&entry.path(), follow_symlinks, visited))
+        .sum()
+}
+
+fn entry_size(path: &Path, follow_symlinks: bool, visited: &Mutex<HashSet<PathBuf>>) -> u64 {
+    let Ok(Some(metadata)) = entry_metadata(path, follow_symlinks) else {
+        return 0;
+    };
+    if metadata.is_dir() {
+        let first_visit =
+            mark_dir_visited(&mut visited.lock().expect("visited set poisoned"), path);
+        if first_visit {
+            dir_size(path, follow_symlinks, visited)
+        } else {
+            0
+        }
+    } else {
+        metadata.len()
+    }
+}
+
+/// Metadata for a directory entry, or `None` for a symlink that should be
+/// skipped. With `follow_symlinks`, links are resolved; dangling ones are skipped.
+fn entry_metadata(path: &Path, follow_symlinks: bool) -> io::Result<Option<fs::Metadata>> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.file_type().is_symlink() {
+        return Ok(Some(metadata));
+    }
+    if !follow_symlinks {
+        return Ok(None);
+    }
+    Ok(fs::metadata(path).ok())
+}
+
+/// Record a directory by its canonical path, returning `false` if it was
+/// already seen. This stops symlink cycles when links are followed.
+pub(crate) fn mark_dir_visited(visited: &mut HashSet<PathBuf>, dir: &Path) -> bool {
+    match fs::canonicalize(dir) {
+        Ok(canonical) => visited.insert(canonical),
+        Err(_) => false,
+    }
+}
+
+/// Create a symlink at `path` pointing to `link_target`, replacing a file or
+/// symlink already there. A real directory at `path` is left alone and
+/// reported as an error.
+pub(crate) fn replace_with_symlink(link_target: &Path, path: &Path) -> io::Result<()> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if metadata.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is a directory", path.display()),
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(link_target, path)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = link_target;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "symlinks can only be preserved on Unix",
+        ))
+    }
+}
+
+/// Whether this process can write to `path`: a file is opened for writing
+/// (without changing it), and a directory gets a probe file created and
+/// removed again. Unlike the permission bits, this accounts for ownership,
+/// ACLs, read-only mounts and running as root.
+pub(crate) fn is_writable(path: &Path) -> bool {
+    if !path.is_dir() {
+        return fs::OpenOptions::new().write(true).open(path).is_ok();
+    }
+    let probe = path.join(format!(".mntn-write-test-{}", std::process::id()));
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, removed on drop.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("mntn-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn write(path: &Path, bytes: usize) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![b'x'; bytes]).unwrap();
+    }
+
+    fn sequential_size(path: &Path, follow_symlinks: bool) -> u64 {
+        let visited = Mutex::new(HashSet::new());
+        mark_dir_visited(&mut visited.lock().unwrap(), path);
+        dir_size(path, follow_symlinks, &visited)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn parallel_size_matches_sequential_walk() {
+        let fixture = Fixture::new("dir-size");
+        let root = &fixture.0;
+        write(&root.join("a.txt"), 10);
+        write(&root.join("sub/b.txt"), 200);
+        write(&root.join("sub/deep/c.txt"), 3000);
+        write(&root.join("other/d.txt"), 40_000);
+        for i in 0..8 {
+            write(&root.join(format!("many/{}/f.txt", i)), 5);
+        }
+        std::os::unix::fs::symlink(root.join("sub"), root.join("link_to_sub")).unwrap();
+        std::os::unix::fs::symlink(root.join("a.txt"), root.join("link_to_a")).unwrap();
+        std::os::unix::fs::symlink(root, root.join("sub/deep/loop")).unwrap();
+
+        let without_links = calculate_dir_size_parallel(root, false);
+        assert_eq!(without_links, sequential_size(root, false));
+        assert_eq!(without_links, 10 + 200 + 3000 + 40_000 + 8 * 5);
+
+        let with_links = calculate_dir_size_parallel(root, true);
+        assert_eq!(with_links, sequential_size(root, true));
+        // The linked file counts again; the linked directories do not.
+        assert_eq!(with_links, without_links + 10);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_keeps_symlinks_as_links() {
+        let fixture = Fixture::new("copy-links");
+        let (src, dst) = (fixture.0.join("src"), fixture.0.join("dst"));
+        write(&src.join("common/.zshrc"), 3);
+        std::os::unix::fs::symlink("/etc/hosts", src.join("common/hosts")).unwrap();
+        std::os::unix::fs::symlink("missing", src.join("common/dangling")).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+
+        copy_dir_keeping_symlinks(&src, &dst).unwrap();
+
+        assert_eq!(fs::read(dst.join("common/.zshrc")).unwrap(), b"xxx");
+        assert_eq!(
+            fs::read_link(dst.join("common/hosts")).unwrap(),
+            Path::new("/etc/hosts")
+        );
+        assert_eq!(
+            fs::read_link(dst.join("common/dangling")).unwrap(),
+            Path::new("missing")
+        );
+    }
+
+    #[test]
+    fn writable_file_and_directory_are_detected_without_changes() {
+        let fixture = Fixture::new("writable");
+        let file = fixture.0.join("config");
+        write(&file, 3);
+
+        assert!(is_writable(&file));
+        assert!(is_writable(&fixture.0));
+        assert!(!is_writable(&fixture.0.join("missing")));
+        assert_eq!(fs::read(&file).unwrap(), b"xxx");
+        assert_eq!(fs::read_dir(&fixture.0).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn missing_or_empty_directory_is_zero() {
+        let fixture = Fixture::new("dir-size-empty");
+        assert_eq!(calculate_dir_size_parallel(&fixture.0, false), 0);
+        assert_eq!(
+            calculate_dir_size_parallel(&fixture.0.join("nope"), true),
+            0
+        );
+    }
+}
@@ -0,0 +1,61 @@
+/// Format a byte count for display. With `binary` set, sizes use 1024-based
+/// units (KiB, MiB, ...) to match `du -h` and most file managers; otherwise
+/// they use 1000-based SI units (kB, MB, ...).
+pub(crate) fn bytes_to_human_readable(bytes: u64, binary: bool) -> String {
+    let (divisor, units) = if binary {
+        (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"])
+    } else {
+        (1000.0, ["B", "kB", "MB", "GB", "TB"])
+    };
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= divisor && unit < units.len() - 1 {
+        size /= divisor;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Render a duration in its largest whole unit, e.g. `2d` or `90m`.
+pub(crate) fn duration_to_human_readable(duration: std::time::Duration) -> String {
+    const UNITS: [(u64, &str); 4] = [
+        (7 * 24 * 60 * 60, "w"),
+        (24 * 60 * 60, "d"),
+        (60 * 60, "h"),
+        (60, "m"),
+    ];
+
+    let seconds = duration.as_secs();
+    for (size, unit) in UNITS {
+        if seconds >= size && seconds.is_multiple_of(size) {
+            return format!("{}{}", seconds / size, unit);
+        }
+    }
+    format!("{}s", seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_units_switch_at_1024() {
+        assert_eq!(bytes_to_human_readable(1023, true), "1023 B");
+        assert_eq!(bytes_to_human_readable(1024, true), "1.0 KiB");
+        assert_eq!(bytes_to_human_readable(1_048_576, true), "1.0 MiB");
+    }
+
+    #[test]
+    fn si_units_switch_at_1000() {
+        assert_eq!(bytes_to_human_readable(999, false), "999 B");
+        assert_eq!(bytes_to_human_readable(1000, false), "1.0 kB");
+        assert_eq!(bytes_to_human_readable(1024, false), "1.0 kB");
+        assert_eq!(bytes_to_human_readable(1_000_000, false), "1.0 MB");
+    }
+}
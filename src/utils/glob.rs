@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+
+/// A registry `target_path` used as a glob (`"glob": true`), split into the
+/// fixed directory it starts from and the wildcard part below it. Matched
+/// files keep their path relative to the base, which is also where they live
+/// under the entry's `source_path` in the backup.
+pub(crate) struct GlobPattern {
+    base: PathBuf,
+    rest: PathBuf,
+}
+
+impl GlobPattern {
+    pub(crate) fn new(pattern: &Path) -> Self {
+        let mut base = PathBuf::new();
+        let mut rest = PathBuf::new();
+        for component in pattern.components() {
+            let text = component.as_os_str().to_string_lossy();
+            if rest.as_os_str().is_empty() && !text.contains(['*', '?', '[']) {
+                base.push(component);
+            } else {
+                rest.push(component);
+            }
+        }
+        Self { base, rest }
+    }
+
+    /// The directory the live files are matched under.
+    pub(crate) fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Regular files matching the wildcard part under `root` (the base, or the
+    /// entry's backup directory), relative to `root` and sorted.
+    pub(crate) fn files_under(&self, root: &Path) -> Result<Vec<PathBuf>, glob::PatternError> {
+        let root_pattern = glob::Pattern::escape(&root.to_string_lossy());
+        let pattern = Path::new(&root_pattern).join(&self.rest);
+        let mut files: Vec<PathBuf> = glob::glob(&pattern.to_string_lossy())?
+            .flatten()
+            .filter(|path| path.is_file())
+            .filter_map(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
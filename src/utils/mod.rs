@@ -1,4 +1,6 @@
 pub mod display;
 pub mod filesystem;
+pub mod format;
+pub mod glob;
 pub mod paths;
 pub mod system;
@@ -1,19 +1,53 @@
 use directories_next::BaseDirs;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
 
 pub(crate) const BACKUP_DIR: &str = "backup";
 pub(crate) const COMMON_DIR: &str = "common";
 pub(crate) const ENCRYPTED_DIR: &str = "encrypted";
 pub(crate) const ENCRYPTED_BUNDLE_FILE: &str = "mntn-encrypted-bundle.age";
+pub(crate) const SNAPSHOTS_DIR: &str = "snapshots";
+pub(crate) const PRE_RESTORE_DIR: &str = "pre-restore";
 
 pub(crate) const PROFILES_DIR: &str = "profiles";
 pub(crate) const PROFILE_CONFIG_FILE: &str = "profiles.json";
 pub(crate) const ACTIVE_PROFILE_FILE: &str = ".active-profile";
+pub(crate) const SETTINGS_FILE: &str = "config.toml";
+pub(crate) const LOCK_FILE: &str = ".lock";
 
+pub(crate) const MNTN_DIR_ENV: &str = "MNTN_DIR";
+
+static MNTN_DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Use `dir` instead of `~/.mntn` for the rest of the run (`--mntn-dir`), or
+/// go back to the default with `None`. Relative paths are taken from the
+/// current directory.
+pub(crate) fn set_mntn_dir(dir: Option<&Path>) {
+    let dir = dir.map(|dir| std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf()));
+    *MNTN_DIR_OVERRIDE.write().unwrap_or_else(|e| e.into_inner()) = dir;
+}
+
+/// The base directory everything else lives under: `--mntn-dir`, then the
+/// `MNTN_DIR` environment variable, then `~/.mntn`.
 pub(crate) fn get_mntn_dir() -> PathBuf {
-    let base_dirs = BaseDirs::new().unwrap();
-    let home_dir = base_dirs.home_dir();
-    home_dir.join(".mntn")
+    if let Some(dir) = MNTN_DIR_OVERRIDE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    {
+        return dir;
+    }
+    match std::env::var_os(MNTN_DIR_ENV) {
+        Some(value) if !value.is_empty() => {
+            let dir = PathBuf::from(value);
+            std::path::absolute(&dir).unwrap_or(dir)
+        }
+        _ => {
+            let base_dirs = BaseDirs::new().unwrap();
+            let home_dir = base_dirs.home_dir();
+            home_dir.join(".mntn")
+        }
+    }
 }
 
 pub(crate) fn get_backup_path() -> PathBuf {
@@ -52,6 +86,18 @@ pub(crate) fn get_packages_path() -> PathBuf {
     get_backup_path().join("packages")
 }
 
+pub(crate) fn get_lock_path() -> PathBuf {
+    get_mntn_dir().join(LOCK_FILE)
+}
+
+pub(crate) fn get_snapshots_path() -> PathBuf {
+    get_mntn_dir().join(SNAPSHOTS_DIR)
+}
+
+pub(crate) fn get_pre_restore_path() -> PathBuf {
+    get_mntn_dir().join(PRE_RESTORE_DIR)
+}
+
 pub(crate) fn get_profiles_config_path() -> PathBuf {
     get_mntn_dir().join(PROFILE_CONFIG_FILE)
 }
@@ -60,6 +106,12 @@ pub(crate) fn get_active_profile_path() -> PathBuf {
     get_mntn_dir().join(ACTIVE_PROFILE_FILE)
 }
 
+pub(crate) fn get_settings_path() -> PathBuf {
+    get_mntn_dir().join(SETTINGS_FILE)
+}
+
+/// `$XDG_CONFIG_HOME/<relative_path>`, or `~/.config/<relative_path>` on every
+/// platform, for tools that use the XDG layout even on macOS and Windows.
 pub(crate) fn get_xdg_or_default_config_path(relative_path: &str) -> PathBuf {
     if let Some(xdg_config) = xdg_config_home_dir() {
         return xdg_config.join(relative_path);
@@ -80,8 +132,8 @@ pub(crate) fn get_ghostty_config_path() -> PathBuf {
     {
         BaseDirs::new()
             .unwrap()
-            .home_dir()
-            .join("Library/Application Support/com.mitchellh.ghostty/config")
+            .data_dir()
+            .join("com.mitchellh.ghostty/config")
     }
 
     #[cfg(not(target_os = "macos"))]
@@ -90,9 +142,197 @@ pub(crate) fn get_ghostty_config_path() -> PathBuf {
     }
 }
 
+/// Re-home an absolute path under `root`, e.g. `/home/me/.zshrc` becomes
+/// `<root>/home/me/.zshrc`. Drive prefixes and the root component are dropped.
+pub(crate) fn reroot_path(root: &Path, path: &Path) -> PathBuf {
+    let relative: PathBuf = path
+        .components()
+        .filter(|component| !matches!(component, Component::Prefix(_) | Component::RootDir))
+        .collect();
+    root.join(relative)
+}
+
+/// Expand a leading `~` / `~user` and any `$VAR` / `${VAR}` references in a
+/// registry path. `~user` is looked up in `/etc/passwd` and is an error when
+/// the user is not listed there (or on Windows). Unset variables and
+/// non-UTF-8 paths are left as-is.
+pub(crate) fn expand_path(path: &Path) -> crate::errors::Result<PathBuf> {
+    let Some(raw) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+    if !raw.contains('~') && !raw.contains('$') {
+        return Ok(path.to_path_buf());
+    }
+
+    let expanded = expand_env_vars(raw, |name| std::env::var(name).ok());
+    let Some(rest) = expanded.strip_prefix('~') else {
+        return Ok(PathBuf::from(expanded));
+    };
+
+    let (user, remainder) = match rest.find(['/', '\\']) {
+        Some(index) => (&rest[..index], &rest[index + 1..]),
+        None => (rest, ""),
+    };
+    let user_home = if user.is_empty() {
+        BaseDirs::new().unwrap().home_dir().to_path_buf()
+    } else {
+        let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+        home_from_passwd(&passwd, user)
+            .ok_or_else(|| crate::errors::AppError::UnknownUser(user.to_string()))?
+    };
+
+    if remainder.is_empty() {
+        Ok(user_home)
+    } else {
+        Ok(user_home.join(remainder))
+    }
+}
+
+/// Write `path` relative to `~` when it lies inside the home directory, the
+/// form [`expand_path`] reads back on any machine.
+pub(crate) fn collapse_home(path: &Path) -> PathBuf {
+    let home = BaseDirs::new().unwrap().home_dir().to_path_buf();
+    match path.strip_prefix(&home) {
+        Ok(rest) if rest.as_os_str().is_empty() => PathBuf::from("~"),
+        Ok(rest) => Path::new("~").join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// The home directory field of `user`'s line in passwd-formatted `contents`.
+fn home_from_passwd(contents: &str, user: &str) -> Option<PathBuf> {
+    contents
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 6 && fields[0] == user)
+        .map(|fields| PathBuf::from(fields[5]))
+        .filter(|home| !home.as_os_str().is_empty())
+}
+
+/// Replace `$VAR` / `${VAR}` references using `lookup`, leaving names it
+/// does not know untouched.
+fn expand_env_vars(input: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(index) = rest.find('$') {
+        output.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        match lookup(name) {
+            Some(value) if !name.is_empty() => output.push_str(&value),
+            _ => output.push_str(&rest[index..index + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
 fn xdg_config_home_dir() -> Option<PathBuf> {
     match std::env::var_os("XDG_CONFIG_HOME") {
         Some(value) if !value.is_empty() => Some(PathBuf::from(value)),
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> PathBuf {
+        BaseDirs::new().unwrap().home_dir().to_path_buf()
+    }
+
+    #[test]
+    fn tilde_expands_to_home() {
+        assert_eq!(expand_path(Path::new("~")).unwrap(), home());
+        assert_eq!(
+            expand_path(Path::new("~/.config/nvim")).unwrap(),
+            home().join(".config/nvim")
+        );
+    }
+
+    #[test]
+    fn paths_without_tilde_or_variables_are_unchanged() {
+        let path = Path::new("/etc/hosts");
+        assert_eq!(expand_path(path).unwrap(), path);
+        let path = Path::new("/tmp/a~b");
+        assert_eq!(expand_path(path).unwrap(), path);
+    }
+
+    #[test]
+    fn tilde_user_is_read_from_passwd() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\n\
+                      alice:x:1000:1000:Alice,,,:/srv/users/alice:/bin/zsh\n";
+        assert_eq!(
+            home_from_passwd(passwd, "alice"),
+            Some(PathBuf::from("/srv/users/alice"))
+        );
+        assert_eq!(
+            home_from_passwd(passwd, "root"),
+            Some(PathBuf::from("/root"))
+        );
+        assert_eq!(home_from_passwd(passwd, "ali"), None);
+        assert_eq!(home_from_passwd("broken line", "broken line"), None);
+    }
+
+    #[test]
+    fn unknown_tilde_user_is_an_error() {
+        let error = expand_path(Path::new("~mntn-no-such-user/.zshrc")).unwrap_err();
+        assert!(error.to_string().contains("~mntn-no-such-user"));
+    }
+
+    #[test]
+    fn home_variable_expands_in_both_forms() {
+        let Ok(home) = std::env::var("HOME") else {
+            return;
+        };
+        assert_eq!(
+            expand_path(Path::new("$HOME/.zshrc")).unwrap(),
+            PathBuf::from(format!("{}/.zshrc", home))
+        );
+        assert_eq!(
+            expand_path(Path::new("${HOME}/.zshrc")).unwrap(),
+            PathBuf::from(format!("{}/.zshrc", home))
+        );
+    }
+
+    #[test]
+    fn braced_variable_can_sit_inside_a_path_component() {
+        let lookup = |name: &str| (name == "APP").then(|| "nvim".to_string());
+        assert_eq!(
+            expand_env_vars("/opt/${APP}-config/init.lua", lookup),
+            "/opt/nvim-config/init.lua"
+        );
+        assert_eq!(
+            expand_env_vars("/opt/$APP-config/init.lua", lookup),
+            "/opt/nvim-config/init.lua"
+        );
+    }
+
+    #[test]
+    fn unset_variables_pass_through() {
+        for raw in [
+            "$MNTN_TEST_UNSET_VAR/.zshrc",
+            "${MNTN_TEST_UNSET_VAR}/.zshrc",
+            "/tmp/${unterminated",
+            "/tmp/cost$",
+        ] {
+            assert_eq!(expand_path(Path::new(raw)).unwrap(), PathBuf::from(raw));
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::errors::{AppError, Result};
-use std::io;
+use crate::utils::display::status;
+use std::collections::HashMap;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::Path;
 use std::process::Command;
 
@@ -25,9 +27,23 @@ pub(crate) fn strip_ansi_codes(input: &str) -> String {
 }
 
 pub(crate) fn run_cmd(cmd: &str, args: &[&str], dir: Option<&Path>) -> Result<String> {
+    run_cmd_with_env(cmd, args, dir, None)
+}
+
+/// [`run_cmd`] with extra environment variables added to the inherited environment.
+pub(crate) fn run_cmd_with_env(
+    cmd: &str,
+    args: &[&str],
+    dir: Option<&Path>,
+    env: Option<&HashMap<String, String>>,
+) -> Result<String> {
     let mut command = Command::new(cmd);
     command.args(args);
 
+    if let Some(vars) = env {
+        command.envs(vars);
+    }
+
     if let Some(d) = dir {
         command.current_dir(d);
     }
@@ -50,17 +66,55 @@ pub(crate) fn run_cmd(cmd: &str, args: &[&str], dir: Option<&Path>) -> Result<St
     Ok(stdout)
 }
 
-pub(crate) fn sync_directory_contents(source: &Path, dest: &Path) -> io::Result<()> {
-    let output = Command::new("rsync")
-        .args(["-av", "--delete"])
-        .arg(format!("{}/", source.display()))
-        .arg(dest)
-        .output()?;
+/// Run a registry hook command line through the platform shell (`sh -c`, or
+/// `cmd /C` on Windows), with `MNTN_TARGET` set to the entry's target path.
+pub(crate) fn run_hook(command_line: &str, target: &Path) -> Result<String> {
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+
+    let env = HashMap::from([(
+        "MNTN_TARGET".to_string(),
+        target.to_string_lossy().into_owned(),
+    )]);
+    run_cmd_with_env(shell, &[flag, command_line], None, Some(&env))
+}
+
+/// Mirror `source` into `dest` with `rsync --delete`. With `verbose`, the
+/// command line and rsync's own output are reported as progress lines.
+pub(crate) fn sync_directory_contents(source: &Path, dest: &Path, verbose: bool) -> Result<()> {
+    let source_arg = format!("{}/", source.display());
+    let mut command = Command::new("rsync");
+    command.args(["-av", "--delete"]).arg(&source_arg).arg(dest);
+    let missing = |e: io::Error| match e.kind() {
+        io::ErrorKind::NotFound => AppError::RsyncMissing,
+        _ => AppError::Io(e),
+    };
+
+    if verbose {
+        status!(
+            "     $ rsync -av --delete {} {}",
+            source_arg,
+            dest.display()
+        );
+    }
+
+    let output = command.output().map_err(missing)?;
+    if verbose {
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            status!("     {}", line);
+        }
+    }
 
     if !output.status.success() {
         let stderr = String::from_utf8(output.stderr.clone())
             .unwrap_or_else(|_| format!("<binary stderr: {} bytes>", output.stderr.len()));
-        return Err(io::Error::other(format!("rsync failed: {}", stderr)));
+        return Err(AppError::CommandFailure {
+            cmd: "rsync".to_string(),
+            status: output.status.code(),
+            stderr,
+        });
     }
 
     Ok(())
@@ -85,3 +139,23 @@ pub(crate) fn is_command_available(command: &str) -> bool {
 
     false
 }
+
+/// Ask a yes/no question on the terminal; anything but `y`/`yes` counts as no.
+/// Fails when stdin is not a terminal, so scripts must opt in explicitly.
+pub(crate) fn confirm(prompt: &str) -> io::Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(io::Error::other(
+            "cannot ask for confirmation: stdin is not a terminal",
+        ));
+    }
+
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}